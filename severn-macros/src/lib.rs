@@ -11,12 +11,22 @@ pub fn severn(args: TokenStream, item: TokenStream) -> TokenStream {
     let MyArgs {
         name_value,
         system_message_value,
+        description_value,
         ..
     } = parse_macro_input!(args as MyArgs);
     let item = parse_macro_input!(item as ItemStruct);
 
     let ident = item.ident.clone();
 
+    //The description is optional, so the trait's default is used if there isn't one
+    let description = description_value.map(|description_value| {
+        quote! {
+            fn description(&self) -> String {
+                #description_value.to_string()
+            }
+        }
+    });
+
     let quote = quote! {
         #item
 
@@ -25,6 +35,8 @@ pub fn severn(args: TokenStream, item: TokenStream) -> TokenStream {
                 #name_value.to_string()
             }
 
+            #description
+
             fn system_message(&self) -> String {
                 #system_message_value.to_string()
             }
@@ -43,6 +55,7 @@ struct MyArgs {
     system_message_ident: Ident,
     equals_sign2: Token![=],
     system_message_value: String,
+    description_value: Option<String>,
 }
 
 impl Parse for MyArgs {
@@ -69,6 +82,29 @@ impl Parse for MyArgs {
             _ => return Err(lookahead.error()),
         };
 
+        let mut description_value = None;
+
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+
+            let description_ident = input.parse::<Ident>()?;
+
+            if description_ident != "description" {
+                return Err(syn::Error::new(
+                    description_ident.span(),
+                    "expected `description`",
+                ));
+            }
+
+            input.parse::<Token![=]>()?;
+
+            let lookahead = input.lookahead1();
+            description_value = match input.parse::<Lit>()? {
+                Lit::Str(str) => Some(str.value()),
+                _ => return Err(lookahead.error()),
+            };
+        }
+
         Ok(Self {
             name_ident,
             equals_sign1,
@@ -77,6 +113,7 @@ impl Parse for MyArgs {
             system_message_ident,
             equals_sign2,
             system_message_value,
+            description_value,
         })
     }
 }
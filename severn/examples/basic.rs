@@ -1,4 +1,3 @@
-use async_openai::{config::OpenAIConfig, Client};
 use severn::{agents::traits::Agent, pipeline::Pipeline};
 use std::sync::Arc;
 
@@ -26,5 +25,5 @@ async fn main() {
 
     assert_eq!(example_agent.name(), String::from("Example agent"));
 
-    let pipeline = Pipeline::new().add_agent(example_agent);
+    let _pipeline = Pipeline::new().add_agent(example_agent);
 }
@@ -1,5 +1,9 @@
 use severn::{
-    agents::traits::Agent, data_sources::qdrant::Qdrant, files::CSVFile, pipeline::Pipeline,
+    agents::traits::Agent,
+    data_sources::qdrant::Qdrant,
+    files::CSVFile,
+    models::{BatchLimits, OpenAI},
+    pipeline::Pipeline,
     qdrant_client::client::QdrantClient,
 };
 
@@ -34,16 +38,20 @@ async fn main() -> anyhow::Result<()> {
     assert_eq!(example_agent.name(), String::from("Example agent"));
 
     let qdrant_client = QdrantClient::from_url("localhost:6334").build()?;
-    let qdrant = Arc::new(Qdrant::new(qdrant_client));
+    let qdrant = Qdrant::new(qdrant_client, "memes".into(), "document".into());
+
+    let embedder = OpenAI::from_env()?
+        .with_embedding_model("text-embedding-3-small")
+        .with_batch_limits(BatchLimits::new().with_max_items(512));
 
     qdrant
-        .embed_and_upsert::<CSVFile>("meme.csv".into())
+        .embed_and_upsert::<CSVFile, _>("meme.csv".into(), embedder)
         .await?;
 
     let pipeline_result = Pipeline::new()
-        .add_data_source(qdrant)
+        .with_model(Arc::new(OpenAI::from_env()?))
         .add_agent(example_agent)
-        .run_pipeline("Hello! This is a prompt.".to_owned())
+        .run_pipeline_with_initial_data("Hello! This is a prompt.".to_owned(), qdrant)
         .await?;
 
     println!("{pipeline_result}");
@@ -4,7 +4,8 @@ use std::sync::Arc;
 
 #[severn(
     name = "Example agent",
-    system_message = "This is an example of using macros with an agent"
+    system_message = "This is an example of using macros with an agent",
+    description = "Shows how to define an agent with macros"
 )]
 struct ExampleAgent;
 
@@ -26,6 +27,10 @@ async fn main() {
         example_agent.system_message(),
         String::from("This is an example of using macros with an agent")
     );
+    assert_eq!(
+        example_agent.description(),
+        String::from("Shows how to define an agent with macros")
+    );
 
-    let pipeline = Pipeline::new().add_agent(example_agent);
+    let _pipeline = Pipeline::new().add_agent(example_agent);
 }
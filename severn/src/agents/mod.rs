@@ -1,2 +1,3 @@
 pub mod premade;
+pub mod react;
 pub mod traits;
@@ -1,8 +1,24 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
 use crate::agents::traits::Agent;
+use crate::models::ModelSettings;
+use crate::structured::OutputSchema;
+use crate::templates::Template;
+
+const ARTICLE_WRITER_TEMPLATE: &str = "You are an AI agent.
+
+Your job is to write an article that involves the data (or summary) that you've been given. Your target audience is {target_audience}.
+
+When answering, your tone should be: {tone}.";
 
 pub struct ArticleWriter {
     target_audience: String,
     tone: String,
+    settings: ModelSettings,
 }
 
 impl Default for ArticleWriter {
@@ -16,6 +32,7 @@ impl ArticleWriter {
         Self {
             target_audience: "software developers".to_string(),
             tone: "concise".to_string(),
+            settings: ModelSettings::default(),
         }
     }
 
@@ -37,6 +54,12 @@ impl ArticleWriter {
     pub fn tone(&self) -> &str {
         &self.tone
     }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
 }
 
 impl Agent for ArticleWriter {
@@ -44,15 +67,35 @@ impl Agent for ArticleWriter {
         "ArticleWriter".into()
     }
 
+    fn description(&self) -> String {
+        format!(
+            "Writes an article for {} from the data or summary it's given.",
+            self.target_audience()
+        )
+    }
+
     fn system_message(&self) -> String {
-        format!("You are an AI agent.
+        Template::new(ARTICLE_WRITER_TEMPLATE).fill(&self.variables())
+    }
 
-         Your job is to write an article that involves the data (or summary) that you've been given. Your target audience is {}.
+    fn system_template(&self) -> Option<Template> {
+        Some(Template::new(ARTICLE_WRITER_TEMPLATE))
+    }
 
-        When answering, your tone should be: {}.",
-                self.target_audience(),
-                self.tone()
-        )
+    /// `target_audience` and `tone` can be overridden for a single run with
+    /// `Pipeline::run_pipeline_with_variables`.
+    fn variables(&self) -> HashMap<String, String> {
+        HashMap::from([
+            (
+                "target_audience".to_string(),
+                self.target_audience.to_owned(),
+            ),
+            ("tone".to_string(), self.tone.to_owned()),
+        ])
+    }
+
+    fn settings(&self) -> ModelSettings {
+        self.settings.clone()
     }
 }
 
@@ -75,6 +118,10 @@ impl Agent for Researcher {
         "Researcher".into()
     }
 
+    fn description(&self) -> String {
+        "Researches the query using the provided context and summarises what it finds.".into()
+    }
+
     fn system_message(&self) -> String {
         "You are an AI agent.
 
@@ -84,3 +131,801 @@ impl Agent for Researcher {
             .to_string()
     }
 }
+
+/// How long a `Summarizer`'s summary should be.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SummaryLength {
+    /// One or two sentences.
+    Short,
+    /// A single paragraph.
+    Medium,
+    /// Several paragraphs.
+    Long,
+    /// At most this many words.
+    Words(usize),
+}
+
+/// How a `Summarizer` lays out its summary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SummaryFormat {
+    Prose,
+    BulletPoints,
+}
+
+pub struct Summarizer {
+    length: SummaryLength,
+    format: SummaryFormat,
+    focus: Option<String>,
+    settings: ModelSettings,
+}
+
+impl Default for Summarizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Summarizer {
+    pub fn new() -> Self {
+        Self {
+            length: SummaryLength::Medium,
+            format: SummaryFormat::Prose,
+            focus: None,
+            settings: ModelSettings::default(),
+        }
+    }
+
+    pub fn with_length(mut self, length: SummaryLength) -> Self {
+        self.length = length;
+
+        self
+    }
+
+    pub fn length(&self) -> SummaryLength {
+        self.length
+    }
+
+    pub fn with_format(mut self, format: SummaryFormat) -> Self {
+        self.format = format;
+
+        self
+    }
+
+    pub fn format(&self) -> SummaryFormat {
+        self.format
+    }
+
+    /// Something the summary should concentrate on, like "the financial figures".
+    pub fn with_focus(mut self, focus: &str) -> Self {
+        self.focus = Some(focus.to_owned());
+
+        self
+    }
+
+    pub fn focus(&self) -> Option<&str> {
+        self.focus.as_deref()
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+}
+
+impl Agent for Summarizer {
+    fn name(&self) -> String {
+        "Summarizer".into()
+    }
+
+    fn description(&self) -> String {
+        "Summarises the text it's given.".into()
+    }
+
+    fn system_message(&self) -> String {
+        let length = match self.length {
+            SummaryLength::Short => "in one or two sentences".to_string(),
+            SummaryLength::Medium => "in a single paragraph".to_string(),
+            SummaryLength::Long => "in several paragraphs".to_string(),
+            SummaryLength::Words(words) => format!("in at most {words} words"),
+        };
+
+        let format = match self.format {
+            SummaryFormat::Prose => "Write the summary as prose.",
+            SummaryFormat::BulletPoints => {
+                "Write the summary as a list of bullet points, one point per line, each starting with \"- \"."
+            }
+        };
+
+        let mut sections = vec![
+            "You are an AI agent.".to_string(),
+            format!("Your job is to summarise the provided context {length}."),
+            format.to_string(),
+        ];
+
+        if let Some(focus) = &self.focus {
+            sections.push(format!("Concentrate on {focus}."));
+        }
+
+        sections.push(
+            "Only include information found in the context, and reply with the summary only."
+                .to_string(),
+        );
+
+        sections.join("\n\n")
+    }
+
+    fn settings(&self) -> ModelSettings {
+        self.settings.clone()
+    }
+}
+
+/// Reviews work against a rubric, scoring each criterion and explaining what to improve.
+pub struct Critic {
+    rubric: Vec<String>,
+    max_score: u32,
+    settings: ModelSettings,
+}
+
+impl Default for Critic {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Critic {
+    pub fn new() -> Self {
+        Self {
+            rubric: vec![
+                "Accuracy".to_string(),
+                "Clarity".to_string(),
+                "Completeness".to_string(),
+            ],
+            max_score: 10,
+            settings: ModelSettings::default(),
+        }
+    }
+
+    /// Replaces the rubric with `criteria`.
+    pub fn with_rubric(mut self, criteria: &[&str]) -> Self {
+        self.rubric = criteria.iter().map(|x| x.to_string()).collect();
+
+        self
+    }
+
+    /// Adds a criterion to the end of the rubric.
+    pub fn with_criterion(mut self, criterion: &str) -> Self {
+        self.rubric.push(criterion.to_owned());
+
+        self
+    }
+
+    pub fn rubric(&self) -> &[String] {
+        &self.rubric
+    }
+
+    /// The highest score a criterion can get. Scores start at 1.
+    pub fn with_max_score(mut self, max_score: u32) -> Self {
+        self.max_score = max_score.max(1);
+
+        self
+    }
+
+    pub fn max_score(&self) -> u32 {
+        self.max_score
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+}
+
+impl Agent for Critic {
+    fn name(&self) -> String {
+        "Critic".into()
+    }
+
+    fn description(&self) -> String {
+        "Reviews work against a rubric, scoring it and suggesting improvements.".into()
+    }
+
+    fn system_message(&self) -> String {
+        let rubric = self
+            .rubric
+            .iter()
+            .map(|criterion| format!("- {criterion}"))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        format!(
+            "You are an AI agent.
+
+Your job is to critically review the work in the provided context against the following rubric:
+{rubric}
+
+For each criterion, give a score from 1 to {} and explain the score in a sentence or two. Finish with an overall verdict and a list of the most important improvements to make.
+
+Be honest and specific. Point to the parts of the work that your scores are based on.",
+            self.max_score
+        )
+    }
+
+    fn settings(&self) -> ModelSettings {
+        self.settings.clone()
+    }
+}
+
+pub struct Translator {
+    target_language: String,
+    source_language: Option<String>,
+    settings: ModelSettings,
+}
+
+impl Translator {
+    pub fn new(target_language: &str) -> Self {
+        Self {
+            target_language: target_language.to_owned(),
+            source_language: None,
+            settings: ModelSettings::default(),
+        }
+    }
+
+    pub fn with_target_language(mut self, target_language: &str) -> Self {
+        self.target_language = target_language.to_owned();
+
+        self
+    }
+
+    pub fn target_language(&self) -> &str {
+        &self.target_language
+    }
+
+    /// The language being translated from. If it isn't set, the model works it out.
+    pub fn with_source_language(mut self, source_language: &str) -> Self {
+        self.source_language = Some(source_language.to_owned());
+
+        self
+    }
+
+    pub fn source_language(&self) -> Option<&str> {
+        self.source_language.as_deref()
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+}
+
+impl Agent for Translator {
+    fn name(&self) -> String {
+        "Translator".into()
+    }
+
+    fn description(&self) -> String {
+        format!("Translates text into {}.", self.target_language)
+    }
+
+    fn system_message(&self) -> String {
+        let source = match &self.source_language {
+            Some(source_language) => format!("from {source_language} "),
+            None => String::new(),
+        };
+
+        format!(
+            "You are an AI agent.
+
+Your job is to translate the provided context {source}into {}.
+
+Keep the meaning, tone and formatting (such as Markdown, lists and line breaks) of the original. Don't translate code, URLs or names. Reply with the translation only.",
+            self.target_language
+        )
+    }
+
+    fn settings(&self) -> ModelSettings {
+        self.settings.clone()
+    }
+}
+
+/// The output of a `Classifier`. Use it with `Pipeline::run_pipeline_structured`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct Classification {
+    /// One of the classifier's labels.
+    pub label: String,
+}
+
+/// Sorts the input into one of a fixed set of labels. The output matches `Classification`, with
+/// the label restricted to the ones given.
+pub struct Classifier {
+    labels: Vec<String>,
+    instructions: Option<String>,
+    settings: ModelSettings,
+}
+
+impl Classifier {
+    /// # Panics
+    ///
+    /// If `labels` is empty, as the output schema would have no label the model could pick.
+    pub fn new(labels: &[&str]) -> Self {
+        assert!(!labels.is_empty(), "Classifier needs at least one label");
+
+        Self {
+            labels: labels.iter().map(|x| x.to_string()).collect(),
+            instructions: None,
+            settings: ModelSettings::default(),
+        }
+    }
+
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.labels.push(label.to_owned());
+
+        self
+    }
+
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// Extra guidance on how to pick a label, like what each one means.
+    pub fn with_instructions(mut self, instructions: &str) -> Self {
+        self.instructions = Some(instructions.to_owned());
+
+        self
+    }
+
+    pub fn instructions(&self) -> Option<&str> {
+        self.instructions.as_deref()
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+}
+
+impl Agent for Classifier {
+    fn name(&self) -> String {
+        "Classifier".into()
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "Classifies the input as one of: {}.",
+            self.labels.join(", ")
+        )
+    }
+
+    fn system_message(&self) -> String {
+        let labels = self
+            .labels
+            .iter()
+            .map(|label| format!("- {label}"))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let mut sections = vec![
+            "You are an AI agent.".to_string(),
+            format!(
+                "Your job is to classify the provided context as exactly one of the following labels:\n{labels}"
+            ),
+        ];
+
+        if let Some(instructions) = &self.instructions {
+            sections.push(instructions.to_owned());
+        }
+
+        sections.push(
+            "Reply with a JSON object with a single `label` field, using the label exactly as written above."
+                .to_string(),
+        );
+
+        sections.join("\n\n")
+    }
+
+    fn settings(&self) -> ModelSettings {
+        self.settings.clone()
+    }
+
+    fn output_schema(&self) -> Option<OutputSchema> {
+        Some(OutputSchema::new(
+            "Classification",
+            json!({
+                "type": "object",
+                "properties": {
+                    "label": { "type": "string", "enum": self.labels }
+                },
+                "required": ["label"],
+                "additionalProperties": false
+            }),
+        ))
+    }
+}
+
+/// Pulls named fields out of unstructured text. By default the output is a JSON object with a
+/// string (or `null`, if it can't be found) for each field. Use `with_output_schema` to extract
+/// into your own type instead.
+pub struct Extractor {
+    fields: Vec<(String, String)>,
+    output_schema: Option<OutputSchema>,
+    settings: ModelSettings,
+}
+
+impl Default for Extractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Extractor {
+    pub fn new() -> Self {
+        Self {
+            fields: Vec::new(),
+            output_schema: None,
+            settings: ModelSettings::default(),
+        }
+    }
+
+    /// Adds a field to extract, with a description of what it is.
+    pub fn with_field(mut self, name: &str, description: &str) -> Self {
+        self.fields.push((name.to_owned(), description.to_owned()));
+
+        self
+    }
+
+    pub fn fields(&self) -> &[(String, String)] {
+        &self.fields
+    }
+
+    /// Uses `output_schema` instead of generating one from the fields, for example
+    /// `OutputSchema::of::<Invoice>()`.
+    pub fn with_output_schema(mut self, output_schema: OutputSchema) -> Self {
+        self.output_schema = Some(output_schema);
+
+        self
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+}
+
+impl Agent for Extractor {
+    fn name(&self) -> String {
+        "Extractor".into()
+    }
+
+    fn description(&self) -> String {
+        "Extracts structured fields from unstructured text.".into()
+    }
+
+    fn system_message(&self) -> String {
+        let mut sections = vec![
+            "You are an AI agent.".to_string(),
+            "Your job is to extract information from the provided context.".to_string(),
+        ];
+
+        if !self.fields.is_empty() {
+            let fields = self
+                .fields
+                .iter()
+                .map(|(name, description)| format!("- {name}: {description}"))
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            sections.push(format!("Extract the following fields:\n{fields}"));
+        }
+
+        sections.push(
+            "Reply with a JSON object. Only use information found in the context, and use null for anything that can't be found rather than guessing."
+                .to_string(),
+        );
+
+        sections.join("\n\n")
+    }
+
+    fn settings(&self) -> ModelSettings {
+        self.settings.clone()
+    }
+
+    fn output_schema(&self) -> Option<OutputSchema> {
+        if let Some(output_schema) = &self.output_schema {
+            return Some(output_schema.clone());
+        }
+
+        let properties = self
+            .fields
+            .iter()
+            .map(|(name, description)| {
+                (
+                    name.to_owned(),
+                    json!({ "type": ["string", "null"], "description": description }),
+                )
+            })
+            .collect::<serde_json::Map<String, Value>>();
+
+        let required = self
+            .fields
+            .iter()
+            .map(|(name, _)| name.to_owned())
+            .collect::<Vec<String>>();
+
+        Some(OutputSchema::new(
+            "Extraction",
+            json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+                "additionalProperties": false
+            }),
+        ))
+    }
+}
+
+pub struct CodeReviewer {
+    language: Option<String>,
+    focus: Vec<String>,
+    settings: ModelSettings,
+}
+
+impl Default for CodeReviewer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CodeReviewer {
+    pub fn new() -> Self {
+        Self {
+            language: None,
+            focus: vec![
+                "correctness and bugs".to_string(),
+                "security".to_string(),
+                "performance".to_string(),
+                "readability".to_string(),
+            ],
+            settings: ModelSettings::default(),
+        }
+    }
+
+    /// The language the code is written in. If it isn't set, the model works it out.
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_owned());
+
+        self
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Replaces what the review concentrates on.
+    pub fn with_focus(mut self, focus: &[&str]) -> Self {
+        self.focus = focus.iter().map(|x| x.to_string()).collect();
+
+        self
+    }
+
+    pub fn focus(&self) -> &[String] {
+        &self.focus
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+}
+
+impl Agent for CodeReviewer {
+    fn name(&self) -> String {
+        "CodeReviewer".into()
+    }
+
+    fn description(&self) -> String {
+        "Reviews code and points out problems with suggested fixes.".into()
+    }
+
+    fn system_message(&self) -> String {
+        let code = match &self.language {
+            Some(language) => format!("the {language} code"),
+            None => "the code".to_string(),
+        };
+
+        let focus = self
+            .focus
+            .iter()
+            .map(|focus| format!("- {focus}"))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        format!(
+            "You are an AI agent and an experienced software engineer.
+
+Your job is to review {code} in the provided context, concentrating on:
+{focus}
+
+For each problem you find, say where it is, why it's a problem and how to fix it, with a code snippet where it helps. Put the most serious problems first. If the code looks good, say so rather than inventing problems."
+        )
+    }
+
+    fn settings(&self) -> ModelSettings {
+        self.settings.clone()
+    }
+}
+
+/// Breaks a goal down into a numbered list of steps. Use `with_output_schema` to get the plan as
+/// JSON instead, as `Pipeline::run_pipeline_with_plan` does.
+pub struct Planner {
+    max_steps: usize,
+    output_schema: Option<OutputSchema>,
+    settings: ModelSettings,
+}
+
+impl Default for Planner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Planner {
+    pub fn new() -> Self {
+        Self {
+            max_steps: 5,
+            output_schema: None,
+            settings: ModelSettings::default(),
+        }
+    }
+
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps.max(1);
+
+        self
+    }
+
+    pub fn max_steps(&self) -> usize {
+        self.max_steps
+    }
+
+    /// Asks for the plan as JSON matching `output_schema`, rather than as a numbered list.
+    pub fn with_output_schema(mut self, output_schema: OutputSchema) -> Self {
+        self.output_schema = Some(output_schema);
+
+        self
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+}
+
+impl Agent for Planner {
+    fn name(&self) -> String {
+        "Planner".into()
+    }
+
+    fn description(&self) -> String {
+        "Breaks a goal down into a short plan of steps.".into()
+    }
+
+    fn system_message(&self) -> String {
+        let steps = match self.max_steps {
+            1 => "1 step".to_string(),
+            max_steps => format!("{max_steps} steps"),
+        };
+
+        let format = match self.output_schema {
+            Some(_) => format!("Plan at most {steps}."),
+            None => format!("Reply with a numbered list of at most {steps}, one per line."),
+        };
+
+        format!(
+            "You are an AI agent.
+
+Your job is to make a plan for the query the user gives you, using the provided context.
+
+{format} Each step should be a single, concrete action. Don't add anything before or after the plan."
+        )
+    }
+
+    fn settings(&self) -> ModelSettings {
+        self.settings.clone()
+    }
+
+    fn output_schema(&self) -> Option<OutputSchema> {
+        self.output_schema.clone()
+    }
+}
+
+/// Edits or proofreads text.
+pub struct Editor {
+    proofread_only: bool,
+    style_guide: Option<String>,
+    settings: ModelSettings,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Self {
+            proofread_only: false,
+            style_guide: None,
+            settings: ModelSettings::default(),
+        }
+    }
+
+    /// Only fixes spelling, grammar and punctuation, without changing the wording otherwise.
+    pub fn with_proofread_only(mut self, proofread_only: bool) -> Self {
+        self.proofread_only = proofread_only;
+
+        self
+    }
+
+    pub fn proofread_only(&self) -> bool {
+        self.proofread_only
+    }
+
+    pub fn with_style_guide(mut self, style_guide: &str) -> Self {
+        self.style_guide = Some(style_guide.to_owned());
+
+        self
+    }
+
+    pub fn style_guide(&self) -> Option<&str> {
+        self.style_guide.as_deref()
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+}
+
+impl Agent for Editor {
+    fn name(&self) -> String {
+        "Editor".into()
+    }
+
+    fn description(&self) -> String {
+        if self.proofread_only {
+            "Proofreads text for spelling, grammar and punctuation.".into()
+        } else {
+            "Edits text to make it clearer and more readable.".into()
+        }
+    }
+
+    fn system_message(&self) -> String {
+        let job = if self.proofread_only {
+            "Your job is to proofread the provided context. Only fix spelling, grammar and punctuation mistakes, and leave the wording as it is otherwise."
+        } else {
+            "Your job is to edit the provided context so it reads clearly and concisely. Fix any mistakes, tighten the wording and improve the flow, while keeping the author's meaning and voice."
+        };
+
+        let mut sections = vec!["You are an AI agent.".to_string(), job.to_string()];
+
+        if let Some(style_guide) = &self.style_guide {
+            sections.push(format!("Follow this style guide:\n{style_guide}"));
+        }
+
+        sections.push("Reply with the edited text only.".to_string());
+
+        sections.join("\n\n")
+    }
+
+    fn settings(&self) -> ModelSettings {
+        self.settings.clone()
+    }
+}
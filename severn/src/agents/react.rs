@@ -0,0 +1,322 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::agents::traits::Agent;
+use crate::data_sources::DataSource;
+use crate::errors::Error;
+use crate::models::{ChatRequest, Message, ModelSettings, PromptModel, Usage};
+use crate::tools::Tool;
+
+/// The most tool calls a `ReActAgent` makes before it's told to answer, by default.
+pub const DEFAULT_MAX_REACT_STEPS: usize = 10;
+
+/// What the agent was told to send back once it has used up its steps.
+const STEP_LIMIT_REACHED: &str =
+    "Error: the step limit has been reached, give your final answer now";
+
+/// A tool the agent called, and what it called it with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReActAction {
+    pub tool: String,
+    pub input: Value,
+}
+
+/// One step of the loop: the reasoning the model gave for a tool call, the call itself and the
+/// tool's result.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReActStep {
+    pub thought: String,
+    pub action: ReActAction,
+    pub observation: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReActRun {
+    /// The model's final answer. If the backend gave up on the tool loop, this is empty.
+    pub answer: String,
+    /// Whether the agent answered without running out of steps.
+    pub completed: bool,
+    /// Every thought, action and observation, in order.
+    pub steps: Vec<ReActStep>,
+    /// The usage of the whole run, if the model reported it. If the backend gave up on the tool
+    /// loop, this is `None`.
+    pub usage: Option<Usage>,
+}
+
+/// The steps taken so far in one run, shared between the agent's tools.
+struct Trace {
+    steps: Vec<ReActStep>,
+    max_steps: usize,
+    limit_reached: bool,
+}
+
+/// Wraps one of the agent's tools so every call has to come with a thought, and records the
+/// thought, the call and its result as a step.
+struct ReActTool {
+    tool: Arc<dyn Tool>,
+    trace: Arc<Mutex<Trace>>,
+}
+
+#[async_trait]
+impl Tool for ReActTool {
+    fn name(&self) -> String {
+        self.tool.name()
+    }
+
+    fn description(&self) -> String {
+        self.tool.description()
+    }
+
+    fn parameters(&self) -> Value {
+        let mut parameters = self.tool.parameters();
+
+        if let Some(object) = parameters.as_object_mut() {
+            if let Some(properties) = object
+                .entry("properties")
+                .or_insert_with(|| json!({}))
+                .as_object_mut()
+            {
+                properties.insert(
+                    "thought".into(),
+                    json!({
+                        "type": "string",
+                        "description": "Your reasoning for calling this tool",
+                    }),
+                );
+            }
+
+            if let Some(required) = object
+                .entry("required")
+                .or_insert_with(|| json!([]))
+                .as_array_mut()
+            {
+                required.push(json!("thought"));
+            }
+        }
+
+        parameters
+    }
+
+    async fn call(&self, mut arguments: Value) -> Result<String, Error> {
+        //The thought is only for the trace, so the tool itself never sees it
+        let thought = arguments
+            .as_object_mut()
+            .and_then(|arguments| arguments.remove("thought"))
+            .and_then(|thought| thought.as_str().map(str::to_owned))
+            .unwrap_or_default();
+
+        {
+            let mut trace = self.trace.lock().unwrap();
+
+            if trace.steps.len() >= trace.max_steps {
+                trace.limit_reached = true;
+
+                return Ok(STEP_LIMIT_REACHED.to_string());
+            }
+        }
+
+        let observation = match self.tool.call(arguments.clone()).await {
+            Ok(res) => res,
+            Err(e) => format!("Error: {e}"),
+        };
+
+        self.trace.lock().unwrap().steps.push(ReActStep {
+            thought,
+            action: ReActAction {
+                tool: self.tool.name(),
+                input: arguments,
+            },
+            observation: observation.clone(),
+        });
+
+        Ok(observation)
+    }
+}
+
+/// An agent that works towards an answer by thinking, calling a tool and reading the result, over
+/// and over until it can answer or runs out of steps.
+///
+/// The tools are called through the model's native tool calling, with a `thought` argument added
+/// to each of them for the model's reasoning. As an `Agent` it can be added to a pipeline like any
+/// other; `run` does the same but also returns the steps it took.
+pub struct ReActAgent {
+    name: String,
+    instructions: String,
+    tools: Vec<Arc<dyn Tool>>,
+    max_steps: usize,
+    settings: ModelSettings,
+}
+
+impl Default for ReActAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReActAgent {
+    pub fn new() -> Self {
+        Self {
+            name: "ReActAgent".to_string(),
+            instructions: String::new(),
+            tools: Vec::new(),
+            max_steps: DEFAULT_MAX_REACT_STEPS,
+            settings: ModelSettings::default(),
+        }
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+
+        self
+    }
+
+    /// Extra instructions added to the system message, for example what the agent is for.
+    pub fn with_instructions(mut self, instructions: &str) -> Self {
+        self.instructions = instructions.to_owned();
+
+        self
+    }
+
+    pub fn instructions(&self) -> &str {
+        &self.instructions
+    }
+
+    pub fn with_tool(mut self, tool: Arc<dyn Tool>) -> Self {
+        self.tools.push(tool);
+
+        self
+    }
+
+    pub fn with_tools(mut self, tools: Vec<Arc<dyn Tool>>) -> Self {
+        self.tools.extend(tools);
+
+        self
+    }
+
+    /// Sets the most tool calls the agent makes. Any calls after that are refused and the model is
+    /// told to answer, and the run is returned with `completed` set to false.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps.max(1);
+
+        self
+    }
+
+    pub fn max_steps(&self) -> usize {
+        self.max_steps
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+
+    pub async fn run<P: PromptModel>(&self, prompt: String, model: P) -> Result<ReActRun, Error> {
+        self.run_with_context(prompt, String::from("None"), &model)
+            .await
+    }
+
+    pub async fn run_with_initial_data<P: PromptModel, D: DataSource>(
+        &self,
+        prompt: String,
+        data_source: D,
+        model: P,
+    ) -> Result<ReActRun, Error> {
+        let context = data_source.retrieve_data().await?;
+
+        self.run_with_context(prompt, context, &model).await
+    }
+
+    async fn run_with_context<P: PromptModel>(
+        &self,
+        prompt: String,
+        context: String,
+        model: &P,
+    ) -> Result<ReActRun, Error> {
+        let trace = self.trace();
+
+        let request = ChatRequest::new()
+            .with_agent(&self.name)
+            .with_message(Message::system(&self.system_message()))
+            .with_message(Message::user(&prompt).with_context(&context))
+            .with_settings(self.settings.clone())
+            .with_tools(self.wrap_tools(&trace));
+
+        let res = model.chat(&request).await;
+
+        let trace = trace.lock().unwrap();
+        let steps = trace.steps.clone();
+
+        match res {
+            Ok(res) => Ok(ReActRun {
+                answer: res.content,
+                completed: !trace.limit_reached,
+                steps,
+                usage: res.usage,
+            }),
+            //The backend stopped the tool loop itself, so there's no answer but the steps are kept
+            Err(Error::ToolLoopLimit(..)) => Ok(ReActRun {
+                answer: String::new(),
+                completed: false,
+                steps,
+                usage: None,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn trace(&self) -> Arc<Mutex<Trace>> {
+        Arc::new(Mutex::new(Trace {
+            steps: Vec::new(),
+            max_steps: self.max_steps,
+            limit_reached: false,
+        }))
+    }
+
+    fn wrap_tools(&self, trace: &Arc<Mutex<Trace>>) -> Vec<Arc<dyn Tool>> {
+        self.tools
+            .iter()
+            .map(|tool| {
+                Arc::new(ReActTool {
+                    tool: tool.clone(),
+                    trace: trace.clone(),
+                }) as Arc<dyn Tool>
+            })
+            .collect()
+    }
+}
+
+impl Agent for ReActAgent {
+    fn name(&self) -> String {
+        self.name.to_owned()
+    }
+
+    fn description(&self) -> String {
+        "Answers by reasoning step by step and calling tools to look things up.".into()
+    }
+
+    fn system_message(&self) -> String {
+        let mut sections = vec![String::from(
+            "You are an AI agent that answers the user's prompt by reasoning step by step and using tools.
+
+Before each tool call, write your reasoning in the call's `thought` argument. Read each tool's result before deciding what to do next. Once you know the answer, reply with it without calling any more tools. Never make up a tool's result.",
+        )];
+
+        if !self.instructions.is_empty() {
+            sections.push(self.instructions.to_owned());
+        }
+
+        sections.join("\n\n")
+    }
+
+    fn settings(&self) -> ModelSettings {
+        self.settings.clone()
+    }
+
+    /// The agent's tools, each asking for a thought and limited to `max_steps` calls per request.
+    fn tools(&self) -> Vec<Arc<dyn Tool>> {
+        self.wrap_tools(&self.trace())
+    }
+}
@@ -1,65 +1,71 @@
-use async_openai::{
-    config::OpenAIConfig,
-    types::{
-        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
-        ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs,
-    },
-    Client,
-};
+use crate::guardrails::Guardrail;
+use crate::models::ModelSettings;
+use crate::structured::OutputSchema;
+use crate::templates::Template;
+use crate::tools::Tool;
+use std::collections::HashMap;
+use std::sync::Arc;
 
-use crate::errors::Error;
-
-#[async_trait::async_trait]
+/// Describes an agent: who it is, what it's told and what it can use. Agents don't call models
+/// themselves - the pipeline sends their requests to whichever `PromptModel` it's been given, so
+/// any agent works with any provider.
 pub trait Agent: Send + Sync {
     fn name(&self) -> String;
+
+    /// A short summary of what the agent does, for people reading the pipeline and for anything
+    /// that has to choose between agents.
+    fn description(&self) -> String {
+        String::new()
+    }
+
     fn system_message(&self) -> String;
 
-    async fn prompt(
-        &self,
-        input: &str,
-        data: String,
-        client: Client<OpenAIConfig>,
-    ) -> Result<String, Error> {
-        let input = format!(
-            "{input}
+    /// A system message with placeholders, used by pipelines instead of `system_message`. The
+    /// placeholders are filled from the run's blackboard (including the variables passed to
+    /// `Pipeline::run_pipeline_with_variables`), then from `variables`.
+    fn system_template(&self) -> Option<Template> {
+        None
+    }
 
-            Provided context:
-            {}
-            ",
-            serde_json::to_string_pretty(&data)?
-        );
-        let res = client
-            .chat()
-            .create(
-                CreateChatCompletionRequestArgs::default()
-                    .model("gpt-4o")
-                    .messages(vec![
-                        //First we add the system message to define what the Agent does
-                        ChatCompletionRequestMessage::System(
-                            ChatCompletionRequestSystemMessageArgs::default()
-                                .content(&self.system_message())
-                                .build()?,
-                        ),
-                        //Then we add our prompt
-                        ChatCompletionRequestMessage::User(
-                            ChatCompletionRequestUserMessageArgs::default()
-                                .content(input)
-                                .build()?,
-                        ),
-                    ])
-                    .build()?,
-            )
-            .await
-            .map(|res| {
-                //We extract the first one
-                match res.choices[0].message.content.clone() {
-                    Some(res) => Ok(res),
-                    None => Err(Error::OptionIsNone),
-                }
-            })??;
+    /// Values for the placeholders in `system_template`, usually taken from the agent's fields.
+    fn variables(&self) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    /// Blackboard keys whose values are handed to this agent as context. If this is empty, the
+    /// agent receives the output of the previous agent instead.
+    fn reads(&self) -> Vec<String> {
+        Vec::new()
+    }
 
-        println!("Retrieved result from prompt: {res}");
+    /// The blackboard key that this agent's output is written under.
+    fn writes(&self) -> String {
+        self.name()
+    }
+
+    /// Model settings for this agent. Anything left unset falls back to the pipeline's defaults.
+    fn settings(&self) -> ModelSettings {
+        ModelSettings::default()
+    }
+
+    /// Tools that the model can call while answering as this agent.
+    fn tools(&self) -> Vec<Arc<dyn Tool>> {
+        Vec::new()
+    }
+
+    /// A JSON schema that this agent's output has to match, if any.
+    fn output_schema(&self) -> Option<OutputSchema> {
+        None
+    }
+
+    /// Guardrails run on the prompt and context before they're sent to the model, after the
+    /// pipeline's own.
+    fn input_guardrails(&self) -> Vec<Arc<dyn Guardrail>> {
+        Vec::new()
+    }
 
-        Ok(res)
+    /// Guardrails run on the model's answer, after the pipeline's own.
+    fn output_guardrails(&self) -> Vec<Arc<dyn Guardrail>> {
+        Vec::new()
     }
 }
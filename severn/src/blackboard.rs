@@ -0,0 +1,86 @@
+use crate::errors::Error;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The key that initial data from a `DataSource` is written under.
+pub const INITIAL_DATA_KEY: &str = "initial_data";
+
+/// Key-value state that lives for a single pipeline run.
+///
+/// Agents declare which keys they read into their prompt with `Agent::reads`, and their output is
+/// written under the key returned by `Agent::writes`.
+#[derive(Clone, Debug, Default)]
+pub struct Blackboard {
+    values: HashMap<String, Value>,
+}
+
+impl Blackboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_value<T: Serialize>(mut self, key: &str, value: T) -> Result<Self, Error> {
+        self.insert(key, value)?;
+
+        Ok(self)
+    }
+
+    pub fn insert<T: Serialize>(&mut self, key: &str, value: T) -> Result<(), Error> {
+        self.values
+            .insert(key.to_owned(), serde_json::to_value(value)?);
+
+        Ok(())
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Error> {
+        match self.values.get(key) {
+            Some(value) => Ok(Some(serde_json::from_value(value.clone())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the value under `key` as text. Strings are returned as-is, anything else is
+    /// rendered as pretty-printed JSON.
+    pub fn get_text(&self, key: &str) -> Option<String> {
+        self.values.get(key).map(|value| match value {
+            Value::String(text) => text.to_owned(),
+            other => serde_json::to_string_pretty(other).unwrap_or_else(|_| other.to_string()),
+        })
+    }
+
+    pub fn get_value(&self, key: &str) -> Option<&Value> {
+        self.values.get(key)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.values.remove(key)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.values.keys()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Renders the given keys as labelled sections so they can be handed to an agent as context.
+    pub fn render(&self, keys: &[String]) -> Result<String, Error> {
+        keys.iter()
+            .map(|key| match self.get_text(key) {
+                Some(text) => Ok(format!("{{{key}}}:\n{text}")),
+                None => Err(Error::BlackboardKeyMissing(key.to_owned())),
+            })
+            .collect::<Result<Vec<String>, Error>>()
+            .map(|sections| sections.join("\n\n"))
+    }
+}
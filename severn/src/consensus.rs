@@ -0,0 +1,244 @@
+use crate::blackboard::Blackboard;
+use crate::errors::Error;
+use crate::models::{Completion, PromptModel, UsageReport};
+use crate::pipeline::Pipeline;
+use crate::{agents::traits::Agent, data_sources::DataSource};
+use futures::future::try_join_all;
+use std::sync::Arc;
+
+pub enum Resolution {
+    /// Pick the answer given by the most agents. Ties go to the answer that was given first.
+    MajorityVote,
+    /// Hand every final answer to a judge agent and return whatever it picks.
+    Judge(Arc<dyn Agent>),
+}
+
+/// The result of a consensus run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsensusRun {
+    /// The answer that was picked.
+    pub output: String,
+    /// Each agent's final answer, in the order the agents were added.
+    pub answers: Vec<String>,
+    /// The tokens used by every agent in every round, and by the judge.
+    pub report: UsageReport,
+}
+
+pub struct Consensus {
+    agents: Vec<Arc<dyn Agent>>,
+    rounds: usize,
+    resolution: Resolution,
+}
+
+impl Default for Consensus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Consensus {
+    pub fn new() -> Self {
+        Self {
+            agents: Vec::new(),
+            rounds: 0,
+            resolution: Resolution::MajorityVote,
+        }
+    }
+
+    pub fn add_agent(mut self, agent: Arc<dyn Agent>) -> Self {
+        self.agents.push(agent);
+
+        self
+    }
+
+    pub fn with_rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds;
+
+        self
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    pub fn with_resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = resolution;
+
+        self
+    }
+
+    pub fn resolution(&self) -> &Resolution {
+        &self.resolution
+    }
+
+    /// Runs the consensus with `model`, returning the picked answer along with every agent's
+    /// final answer and the tokens used.
+    pub async fn run(
+        &self,
+        prompt: String,
+        model: Arc<dyn PromptModel>,
+    ) -> Result<ConsensusRun, Error> {
+        self.run_in(
+            &Pipeline::new().with_model(model),
+            &prompt,
+            String::from("None"),
+        )
+        .await
+    }
+
+    pub async fn run_with_initial_data<D: DataSource>(
+        &self,
+        prompt: String,
+        model: Arc<dyn PromptModel>,
+        data_source: D,
+    ) -> Result<ConsensusRun, Error> {
+        let context = data_source.retrieve_data().await?;
+
+        self.run_in(&Pipeline::new().with_model(model), &prompt, context)
+            .await
+    }
+
+    /// Runs the debate and picks the answer, prompting every agent through `pipeline` so that its
+    /// models, settings, guardrails and templates apply.
+    pub(crate) async fn run_in(
+        &self,
+        pipeline: &Pipeline,
+        prompt: &str,
+        context: String,
+    ) -> Result<ConsensusRun, Error> {
+        let mut report = UsageReport::new();
+        let answers = self.debate(pipeline, prompt, context, &mut report).await?;
+
+        let output = match &self.resolution {
+            Resolution::MajorityVote => {
+                majority_vote(answers.to_owned()).ok_or(Error::NoAgentsExist)?
+            }
+            Resolution::Judge(judge) => {
+                let judge_prompt = format!(
+                    "{prompt}\n\n\
+                    Several agents have answered the query above. Pick the answer that is most accurate and reply with that answer only."
+                );
+
+                let res = prompt_agent(
+                    pipeline,
+                    &judge_prompt,
+                    self.format_answers(&answers, None),
+                    judge,
+                )
+                .await?;
+
+                report.record_completion(&judge.name(), &res, pipeline.prices());
+
+                res.content
+            }
+        };
+
+        Ok(ConsensusRun {
+            output,
+            answers,
+            report,
+        })
+    }
+
+    async fn debate(
+        &self,
+        pipeline: &Pipeline,
+        prompt: &str,
+        context: String,
+        report: &mut UsageReport,
+    ) -> Result<Vec<String>, Error> {
+        if self.agents.is_empty() {
+            return Err(Error::NoAgentsExist);
+        }
+
+        let completions = try_join_all(
+            self.agents
+                .iter()
+                .map(|agent| prompt_agent(pipeline, prompt, context.to_owned(), agent)),
+        )
+        .await?;
+
+        let mut answers = self.record(completions, pipeline, report);
+
+        for _ in 0..self.rounds {
+            let revision_prompt = format!(
+                "{prompt}\n\n\
+                You have already answered the query above. The answers from the other agents are in the provided context. Taking them into account, give your revised answer."
+            );
+
+            let completions = try_join_all(self.agents.iter().enumerate().map(|(idx, agent)| {
+                let others = self.format_answers(&answers, Some(idx));
+                let context = format!("Your previous answer:\n{}\n\n{others}", answers[idx]);
+
+                prompt_agent(pipeline, &revision_prompt, context, agent)
+            }))
+            .await?;
+
+            answers = self.record(completions, pipeline, report);
+        }
+
+        Ok(answers)
+    }
+
+    //Completions come back in the same order as the agents
+    fn record(
+        &self,
+        completions: Vec<Completion>,
+        pipeline: &Pipeline,
+        report: &mut UsageReport,
+    ) -> Vec<String> {
+        self.agents
+            .iter()
+            .zip(completions)
+            .map(|(agent, res)| {
+                report.record_completion(&agent.name(), &res, pipeline.prices());
+
+                res.content
+            })
+            .collect()
+    }
+
+    fn format_answers(&self, answers: &[String], skip: Option<usize>) -> String {
+        self.agents
+            .iter()
+            .zip(answers)
+            .enumerate()
+            .filter(|(idx, _)| Some(*idx) != skip)
+            .map(|(_, (agent, answer))| format!("Answer from {}:\n{answer}", agent.name()))
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    }
+}
+
+async fn prompt_agent(
+    pipeline: &Pipeline,
+    prompt: &str,
+    context: String,
+    agent: &Arc<dyn Agent>,
+) -> Result<Completion, Error> {
+    pipeline
+        .prompt_agent(prompt, &[], context, &Blackboard::new(), agent)
+        .await
+}
+
+fn majority_vote(answers: Vec<String>) -> Option<String> {
+    let mut tally: Vec<(String, usize)> = Vec::new();
+
+    for answer in answers {
+        let normalised = answer.trim().to_lowercase();
+
+        match tally
+            .iter_mut()
+            .find(|(existing, _)| existing.trim().to_lowercase() == normalised)
+        {
+            Some((_, count)) => *count += 1,
+            None => tally.push((answer, 1)),
+        }
+    }
+
+    tally
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(answer, _)| answer)
+}
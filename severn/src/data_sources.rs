@@ -1,26 +1,36 @@
 use crate::errors::Error;
+use crate::models::Image;
 
 #[async_trait::async_trait]
 pub trait DataSource: Send + Sync {
     async fn retrieve_data(&self) -> Result<String, Error>;
+
+    /// Images to attach to the prompt alongside the data. Most data sources only have text.
+    async fn retrieve_images(&self) -> Result<Vec<Image>, Error> {
+        Ok(Vec::new())
+    }
 }
 
 #[cfg(feature = "qdrant")]
 pub mod qdrant {
     use crate::data_sources::DataSource;
-    use async_openai::config::OpenAIConfig;
-    use async_openai::types::{CreateEmbeddingRequest, EmbeddingInput};
-    use async_openai::{Client, Embeddings};
+    use crate::models::EmbedModel;
     use qdrant_client::client::Payload;
     use qdrant_client::prelude::QdrantClient;
+    use qdrant_client::qdrant::value::Kind;
     use qdrant_client::qdrant::with_payload_selector::SelectorOptions;
     use qdrant_client::qdrant::PointStruct;
+    use qdrant_client::qdrant::{Condition, Filter};
     use qdrant_client::qdrant::{ScoredPoint, SearchPoints, WithPayloadSelector};
 
     use std::path::PathBuf;
 
     use crate::errors::Error;
     use crate::files::File;
+    use crate::memory::{MemoryRecord, Recalled, VectorStore};
+
+    /// How many points are sent to Qdrant in a single upsert.
+    pub const UPSERT_BATCH_SIZE: usize = 256;
 
     pub struct Qdrant {
         client: QdrantClient,
@@ -29,14 +39,7 @@ pub mod qdrant {
     }
 
     impl Qdrant {
-        pub fn new(client: QdrantClient, collection_Name: String, payload_field: String) -> Self {
-            let api_key = std::env::var("OPENAI_API_KEY").expect("No OPENAI_API_KEY");
-            let config = OpenAIConfig::new()
-                .with_api_key(api_key)
-                .with_org_id("severn");
-
-            let openai_client = Client::with_config(config);
-
+        pub fn new(client: QdrantClient, collection_name: String, payload_field: String) -> Self {
             Self {
                 client,
                 collection_name,
@@ -58,22 +61,42 @@ pub mod qdrant {
             embedder: E,
         ) -> anyhow::Result<()> {
             let file = T::from_filepath(path)?;
+            let chunks = file.parse();
 
-            let embeddings = embedder.embed_file(file.parse()).await?;
+            //The embedder splits the chunks into batches, so big files don't go in one request
+            let embeddings = embedder.embed_file(chunks.to_owned()).await?;
 
-            for embedding in embeddings {
-                self.upsert_embedding(
-                    embedding,
-                    serde_json::json!({
-                        "document": file.contents()
-                    })
-                    .try_into()
-                    .unwrap(),
-                )
-                .await
-                .unwrap();
+            if embeddings.len() != chunks.len() {
+                return Err(anyhow::anyhow!(
+                    "Expected {} embeddings but got {}",
+                    chunks.len(),
+                    embeddings.len()
+                ));
             }
 
+            //Each point gets the chunk it was embedded from, so a search returns the part of the
+            //file that matched
+            let points = chunks
+                .into_iter()
+                .zip(embeddings)
+                .map(|(chunk, embedding)| {
+                    let mut payload = Payload::new();
+                    payload.insert(self.payload_field.to_owned(), chunk);
+
+                    PointStruct::new(uuid::Uuid::new_v4().to_string(), embedding, payload)
+                })
+                .collect::<Vec<PointStruct>>();
+
+            self.client
+                .upsert_points_batch(
+                    self.collection_name.to_owned(),
+                    None,
+                    points,
+                    None,
+                    UPSERT_BATCH_SIZE,
+                )
+                .await?;
+
             Ok(())
         }
 
@@ -123,6 +146,64 @@ pub mod qdrant {
         }
     }
 
+    /// Memories are stored as points with the text under the payload field and the scope under
+    /// `scope`, so the collection's vectors need to match the `Memory`'s embedder.
+    #[async_trait::async_trait]
+    impl VectorStore for Qdrant {
+        async fn insert(&self, record: MemoryRecord) -> Result<(), Error> {
+            let mut payload = Payload::new();
+            payload.insert(self.payload_field.to_owned(), record.text);
+            payload.insert("scope", record.scope);
+
+            self.upsert_embedding(record.embedding, payload)
+                .await
+                .map_err(|e| Error::VectorStoreError(e.to_string()))
+        }
+
+        async fn search(
+            &self,
+            scope: &str,
+            embedding: &[f32],
+            limit: usize,
+        ) -> Result<Vec<Recalled>, Error> {
+            let search_points = SearchPoints {
+                collection_name: self.collection_name.to_owned(),
+                vector: embedding.to_vec(),
+                filter: Some(Filter::must([Condition::matches(
+                    "scope",
+                    scope.to_owned(),
+                )])),
+                limit: limit as u64,
+                with_payload: Some(WithPayloadSelector {
+                    selector_options: Some(SelectorOptions::Enable(true)),
+                }),
+                ..Default::default()
+            };
+
+            let search_result = self
+                .client
+                .search_points(&search_points)
+                .await
+                .map_err(|e| Error::VectorStoreError(e.to_string()))?;
+
+            let memories = search_result
+                .result
+                .into_iter()
+                .filter_map(
+                    |point| match point.payload.get(self.payload_field())?.kind {
+                        Some(Kind::StringValue(ref text)) => Some(Recalled {
+                            text: text.to_owned(),
+                            score: point.score,
+                        }),
+                        _ => None,
+                    },
+                )
+                .collect();
+
+            Ok(memories)
+        }
+    }
+
     #[async_trait::async_trait]
     impl DataSource for Qdrant {
         async fn retrieve_data(&self) -> Result<String, Error> {
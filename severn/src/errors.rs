@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use async_openai::error::OpenAIError;
 use thiserror::Error;
 
@@ -5,12 +7,69 @@ use thiserror::Error;
 pub enum Error {
     #[error("LLM error: {0}")]
     LLMError(#[from] OpenAIError),
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+    #[error("Invalid header value: {0}")]
+    InvalidHeaderValue(#[from] reqwest::header::InvalidHeaderValue),
+    #[error("Model provider returned an error ({status}): {message}")]
+    ProviderError { status: u16, message: String },
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Embedding error: {0}")]
+    EmbeddingError(String),
+    #[error("Vector store error: {0}")]
+    VectorStoreError(String),
     #[error("serde_json error: {0}")]
     SerdeError(#[from] serde_json::error::Error),
     #[error("There's no agents in the pipeline!")]
     NoAgentsExist,
+    #[error("No model has been set for agent: {0}")]
+    NoModelSet(String),
+    #[error("Agent {0} called tools more than {1} times without giving an answer")]
+    ToolLoopLimit(String, usize),
+    #[error("Output did not match the expected schema: {0}")]
+    InvalidStructuredOutput(String),
     #[error("Option expected to be Some but is None")]
     OptionIsNone,
     #[error("Searched data source but no results")]
     DataSourceNoMatch,
+    #[error("Blackboard has no value for key: {0}")]
+    BlackboardKeyMissing(String),
+    #[error("No memory has been set for the pipeline")]
+    NoMemorySet,
+    #[error("There's no agent named: {0}")]
+    NoAgentNamed(String),
+    #[error("The planner returned a plan with no steps")]
+    EmptyPlan,
+    #[error("The planner returned an invalid step: {0}")]
+    InvalidPlanStep(String),
+    #[error("Guardrail {guardrail} stopped agent {agent}: {reason}")]
+    GuardrailViolation {
+        guardrail: String,
+        agent: String,
+        reason: String,
+    },
+    #[error("Invalid regex: {0}")]
+    RegexError(#[from] regex::Error),
+    #[error("Agent {agent} has unresolved placeholders in its system message: {}", placeholders.join(", "))]
+    UnresolvedPlaceholders {
+        agent: String,
+        placeholders: Vec<String>,
+    },
+    #[error("Invalid agent definition: {0}")]
+    InvalidAgentDefinition(String),
+    #[error("There's already an agent named {name} (defined in {first}, then in {second})")]
+    DuplicateAgent {
+        name: String,
+        first: String,
+        second: String,
+    },
+    #[error("Mock model has no response for agent: {0}")]
+    NoMockResponse(String),
+    #[error("Invalid image: {0}")]
+    InvalidImage(String),
+    #[error("The model for agent {0} doesn't support images")]
+    ImagesNotSupported(String),
+    #[error("Rate limited by the model provider")]
+    RateLimited { retry_after: Option<Duration> },
 }
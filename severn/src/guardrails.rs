@@ -0,0 +1,321 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use regex::Regex;
+
+use crate::errors::Error;
+use crate::structured::OutputSchema;
+
+/// How many times an agent is re-asked after its output fails a guardrail, before the pipeline
+/// gives up with `Error::GuardrailViolation`.
+pub const MAX_GUARDRAIL_REASKS: usize = 2;
+
+/// What a guardrail decided about a piece of text.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+    Allow,
+    /// Use this text instead.
+    Rewrite(String),
+    /// Stop the run, with the reason why.
+    Block(String),
+    /// Ask the agent to try again, telling it what was wrong. Only applies to outputs - an input
+    /// that gets a re-ask is blocked.
+    Reask(String),
+}
+
+/// Checks, and optionally rewrites, the text going into or coming out of an agent.
+///
+/// Guardrails can be attached to an agent with `Agent::input_guardrails` and
+/// `Agent::output_guardrails`, or to every agent in a pipeline with
+/// `Pipeline::with_input_guardrail` and `Pipeline::with_output_guardrail`.
+#[async_trait]
+pub trait Guardrail: Send + Sync {
+    fn name(&self) -> String;
+
+    async fn check(&self, text: &str) -> Verdict;
+}
+
+/// Text that made it through every guardrail, or the guardrail that asked for a re-ask.
+pub(crate) enum Checked {
+    Passed(String),
+    Reask { guardrail: String, feedback: String },
+}
+
+/// Runs the guardrails in order, passing any rewrites on to the next one. A block is returned as
+/// `Error::GuardrailViolation`.
+pub(crate) async fn run_guardrails(
+    guardrails: &[Arc<dyn Guardrail>],
+    agent: &str,
+    text: &str,
+) -> Result<Checked, Error> {
+    let mut text = text.to_owned();
+
+    for guardrail in guardrails {
+        match guardrail.check(&text).await {
+            Verdict::Allow => {}
+            Verdict::Rewrite(rewritten) => text = rewritten,
+            Verdict::Block(reason) => {
+                return Err(Error::GuardrailViolation {
+                    guardrail: guardrail.name(),
+                    agent: agent.to_owned(),
+                    reason,
+                })
+            }
+            Verdict::Reask(feedback) => {
+                return Ok(Checked::Reask {
+                    guardrail: guardrail.name(),
+                    feedback,
+                })
+            }
+        }
+    }
+
+    Ok(Checked::Passed(text))
+}
+
+/// Runs the guardrails on an input, where there's nothing to re-ask, so a re-ask blocks.
+pub(crate) async fn check_input(
+    guardrails: &[Arc<dyn Guardrail>],
+    agent: &str,
+    text: &str,
+) -> Result<String, Error> {
+    match run_guardrails(guardrails, agent, text).await? {
+        Checked::Passed(text) => Ok(text),
+        Checked::Reask {
+            guardrail,
+            feedback,
+        } => Err(Error::GuardrailViolation {
+            guardrail,
+            agent: agent.to_owned(),
+            reason: feedback,
+        }),
+    }
+}
+
+/// Limits the text to a number of characters. Over-long text is re-asked, or cut short if
+/// `with_truncate` is set.
+pub struct MaxLength {
+    max_chars: usize,
+    truncate: bool,
+}
+
+impl MaxLength {
+    pub fn new(max_chars: usize) -> Self {
+        Self {
+            max_chars,
+            truncate: false,
+        }
+    }
+
+    pub fn with_truncate(mut self, truncate: bool) -> Self {
+        self.truncate = truncate;
+
+        self
+    }
+}
+
+#[async_trait]
+impl Guardrail for MaxLength {
+    fn name(&self) -> String {
+        "MaxLength".into()
+    }
+
+    async fn check(&self, text: &str) -> Verdict {
+        let length = text.chars().count();
+
+        if length <= self.max_chars {
+            return Verdict::Allow;
+        }
+
+        match self.truncate {
+            true => Verdict::Rewrite(text.chars().take(self.max_chars).collect()),
+            false => Verdict::Reask(format!(
+                "The answer is {length} characters long, but it can be at most {} characters.",
+                self.max_chars
+            )),
+        }
+    }
+}
+
+/// Blocks text that matches any of the patterns.
+pub struct DenyList {
+    patterns: Vec<Regex>,
+}
+
+impl DenyList {
+    pub fn new(patterns: &[&str]) -> Result<Self, Error> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<Regex>, regex::Error>>()?;
+
+        Ok(Self { patterns })
+    }
+}
+
+#[async_trait]
+impl Guardrail for DenyList {
+    fn name(&self) -> String {
+        "DenyList".into()
+    }
+
+    async fn check(&self, text: &str) -> Verdict {
+        match self.patterns.iter().find(|pattern| pattern.is_match(text)) {
+            Some(pattern) => Verdict::Block(format!("the text matched `{pattern}`")),
+            None => Verdict::Allow,
+        }
+    }
+}
+
+/// Replaces email addresses, phone numbers and credit card numbers with placeholders.
+pub struct RedactPii {
+    email: Regex,
+    credit_card: Regex,
+    phone: Regex,
+}
+
+impl Default for RedactPii {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RedactPii {
+    pub fn new() -> Self {
+        Self {
+            email: Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap(),
+            credit_card: Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").unwrap(),
+            phone: Regex::new(
+                r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{2,4}\)|\b\d{2,4})[ .-]\d{3,4}[ .-]\d{3,4}\b",
+            )
+            .unwrap(),
+        }
+    }
+}
+
+#[async_trait]
+impl Guardrail for RedactPii {
+    fn name(&self) -> String {
+        "RedactPii".into()
+    }
+
+    async fn check(&self, text: &str) -> Verdict {
+        let redacted = self.email.replace_all(text, "[EMAIL]");
+
+        //Card numbers are checked first, since they'd also look like phone numbers. Anything that
+        //fails the Luhn check is left for the phone pattern
+        let redacted =
+            self.credit_card
+                .replace_all(&redacted, |caps: &regex::Captures| match luhn(&caps[0]) {
+                    true => String::from("[CREDIT_CARD]"),
+                    false => caps[0].to_owned(),
+                });
+
+        let redacted = self.phone.replace_all(&redacted, "[PHONE]");
+
+        match redacted == text {
+            true => Verdict::Allow,
+            false => Verdict::Rewrite(redacted.into_owned()),
+        }
+    }
+}
+
+fn luhn(number: &str) -> bool {
+    let digits = number
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .collect::<Vec<u32>>();
+
+    let sum = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(idx, digit)| match idx % 2 {
+            1 if digit * 2 > 9 => digit * 2 - 9,
+            1 => digit * 2,
+            _ => *digit,
+        })
+        .sum::<u32>();
+
+    sum % 10 == 0
+}
+
+/// Re-asks unless the text mentions every keyword. Matching ignores case.
+pub struct RequiredKeywords {
+    keywords: Vec<String>,
+}
+
+impl RequiredKeywords {
+    pub fn new(keywords: &[&str]) -> Self {
+        Self {
+            keywords: keywords.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl Guardrail for RequiredKeywords {
+    fn name(&self) -> String {
+        "RequiredKeywords".into()
+    }
+
+    async fn check(&self, text: &str) -> Verdict {
+        let text = text.to_lowercase();
+
+        let missing = self
+            .keywords
+            .iter()
+            .filter(|keyword| !text.contains(&keyword.to_lowercase()))
+            .map(String::as_str)
+            .collect::<Vec<&str>>();
+
+        match missing.is_empty() {
+            true => Verdict::Allow,
+            false => Verdict::Reask(format!(
+                "The answer has to mention: {}.",
+                missing.join(", ")
+            )),
+        }
+    }
+}
+
+/// Re-asks unless the text is valid JSON, and matches the schema if one is set.
+#[derive(Default)]
+pub struct ValidJson {
+    schema: Option<OutputSchema>,
+}
+
+impl ValidJson {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_schema(mut self, schema: OutputSchema) -> Self {
+        self.schema = Some(schema);
+
+        self
+    }
+}
+
+#[async_trait]
+impl Guardrail for ValidJson {
+    fn name(&self) -> String {
+        "ValidJson".into()
+    }
+
+    async fn check(&self, text: &str) -> Verdict {
+        let res = match &self.schema {
+            Some(schema) => schema.validate(text).map(|_| ()),
+            None => serde_json::from_str::<serde_json::Value>(text)
+                .map(|_| ())
+                .map_err(|e| Error::InvalidStructuredOutput(e.to_string())),
+        };
+
+        match res {
+            Ok(()) => Verdict::Allow,
+            Err(e) => Verdict::Reask(format!(
+                "The answer has to be valid JSON, with no other text. {e}"
+            )),
+        }
+    }
+}
@@ -1,6 +1,10 @@
 pub mod agents;
+pub mod blackboard;
+pub mod consensus;
 pub mod data_sources;
 pub mod files;
+pub mod guardrails;
+pub mod memory;
 
 #[cfg(feature = "macros")]
 pub use severn_macros::severn as severn_agent;
@@ -10,7 +14,15 @@ pub use data_sources::qdrant;
 
 #[cfg(feature = "qdrant")]
 pub use qdrant_client;
+
+pub use schemars;
 pub mod errors;
 pub mod pipeline;
+pub mod planning;
 
 pub mod models;
+pub mod rate_limit;
+pub mod registry;
+pub mod structured;
+pub mod templates;
+pub mod tools;
@@ -0,0 +1,182 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::errors::Error;
+use crate::models::EmbedModel;
+
+/// How many memories are recalled for each prompt, by default.
+pub const DEFAULT_TOP_K: usize = 5;
+
+/// Something an agent remembered, along with the embedding it's searched by. `scope` keeps
+/// memories apart, for example one scope per customer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryRecord {
+    pub scope: String,
+    pub text: String,
+    pub embedding: Vec<f32>,
+}
+
+/// A memory that matched a search. Higher scores are closer matches.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recalled {
+    pub text: String,
+    pub score: f32,
+}
+
+/// Where memories are kept. `InMemoryStore` keeps them for the life of the process; with the
+/// `qdrant` feature, `Qdrant` keeps them in a collection so they last across sessions.
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    async fn insert(&self, record: MemoryRecord) -> Result<(), Error>;
+
+    /// The `limit` memories in `scope` closest to `embedding`, best match first.
+    async fn search(
+        &self,
+        scope: &str,
+        embedding: &[f32],
+        limit: usize,
+    ) -> Result<Vec<Recalled>, Error>;
+}
+
+/// A vector store that keeps everything in memory and searches by cosine similarity.
+#[derive(Debug, Default)]
+pub struct InMemoryStore {
+    records: Mutex<Vec<MemoryRecord>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn records(&self) -> Vec<MemoryRecord> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl VectorStore for InMemoryStore {
+    async fn insert(&self, record: MemoryRecord) -> Result<(), Error> {
+        self.records.lock().unwrap().push(record);
+
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        scope: &str,
+        embedding: &[f32],
+        limit: usize,
+    ) -> Result<Vec<Recalled>, Error> {
+        let mut matches = self
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|record| record.scope == scope)
+            .map(|record| Recalled {
+                text: record.text.to_owned(),
+                score: cosine_similarity(&record.embedding, embedding),
+            })
+            .collect::<Vec<Recalled>>();
+
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(limit);
+
+        Ok(matches)
+    }
+}
+
+/// Long-term memory for agents. Memories are embedded with an `EmbedModel` and kept in a
+/// `VectorStore`, and the closest ones to a prompt can be recalled into an agent's context.
+pub struct Memory {
+    embedder: Arc<dyn EmbedModel>,
+    store: Arc<dyn VectorStore>,
+    top_k: usize,
+}
+
+impl Memory {
+    pub fn new(embedder: Arc<dyn EmbedModel>, store: Arc<dyn VectorStore>) -> Self {
+        Self {
+            embedder,
+            store,
+            top_k: DEFAULT_TOP_K,
+        }
+    }
+
+    /// Sets how many memories are recalled for each prompt.
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+
+        self
+    }
+
+    pub fn top_k(&self) -> usize {
+        self.top_k
+    }
+
+    pub fn store(&self) -> &Arc<dyn VectorStore> {
+        &self.store
+    }
+
+    pub async fn remember(&self, scope: &str, text: &str) -> Result<(), Error> {
+        let embedding = self.embed(text).await?;
+
+        self.store
+            .insert(MemoryRecord {
+                scope: scope.to_owned(),
+                text: text.to_owned(),
+                embedding,
+            })
+            .await
+    }
+
+    /// The memories in `scope` closest to `query`, best match first.
+    pub async fn recall(&self, scope: &str, query: &str) -> Result<Vec<Recalled>, Error> {
+        if self.top_k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let embedding = self.embed(query).await?;
+
+        self.store.search(scope, &embedding, self.top_k).await
+    }
+
+    /// The recalled memories, ready to go in front of an agent's context. Empty if nothing was
+    /// recalled.
+    pub(crate) async fn recall_context(&self, scope: &str, query: &str) -> Result<String, Error> {
+        let memories = self.recall(scope, query).await?;
+
+        if memories.is_empty() {
+            return Ok(String::new());
+        }
+
+        let memories = memories
+            .iter()
+            .map(|memory| format!("- {}", memory.text))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Ok(format!("Relevant memories:\n{memories}"))
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, Error> {
+        self.embedder
+            .embed_sentence(text)
+            .await
+            .map_err(|e| Error::EmbeddingError(e.to_string()))
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    dot / (norm_a * norm_b)
+}
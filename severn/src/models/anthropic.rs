@@ -0,0 +1,470 @@
+use crate::models::streaming::{lines, single_response};
+use crate::models::usage::add_usage;
+use crate::models::{
+    error_for_status, ChatRequest, Completion, ContentPart, Message, PromptModel, Role,
+    StreamEvent, TokenStream, ToolCall, Usage,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::{Client, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::errors::Error;
+use crate::tools::{call_tool, MAX_TOOL_ITERATIONS};
+
+pub const ANTHROPIC_API_BASE: &str = "https://api.anthropic.com";
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// A `PromptModel` backed by the Anthropic Messages API.
+#[derive(Clone)]
+pub struct Anthropic {
+    http: Client,
+    api_key: String,
+    base_url: String,
+    model: String,
+    max_tokens: u32,
+}
+
+impl Anthropic {
+    pub fn from_env() -> Result<Self> {
+        let api_key = std::env::var("ANTHROPIC_API_KEY")?;
+
+        Self::from_api_key(&api_key)
+    }
+
+    pub fn from_api_key(api_key: &str) -> Result<Self> {
+        Ok(Self {
+            http: Client::new(),
+            api_key: api_key.to_owned(),
+            base_url: ANTHROPIC_API_BASE.to_string(),
+            model: "claude-3-5-sonnet-latest".to_string(),
+            max_tokens: 4096,
+        })
+    }
+
+    /// Points the client at a different host, for example a proxy or a local mock server.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_owned();
+
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_owned();
+
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+
+        self
+    }
+
+    pub fn max_tokens(&self) -> u32 {
+        self.max_tokens
+    }
+
+    pub fn with_http_client(mut self, http: Client) -> Self {
+        self.http = http;
+
+        self
+    }
+
+    async fn send(&self, request: &MessagesRequest<'_>) -> Result<Response, Error> {
+        let res = self
+            .http
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(request)
+            .send()
+            .await?;
+
+        error_for_status(res).await
+    }
+}
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    system: String,
+    messages: &'a [RequestMessage],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    tools: &'a [ToolDefinition],
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Serialize)]
+struct RequestMessage {
+    role: &'static str,
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Serialize)]
+struct ToolDefinition {
+    name: String,
+    description: String,
+    input_schema: Value,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    usage: Option<MessagesUsage>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Image {
+        source: ImageSource,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ImageSource {
+    #[serde(rename = "type")]
+    kind: String,
+    media_type: String,
+    data: String,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamingEvent {
+    MessageStart {
+        message: StreamingMessage,
+    },
+    ContentBlockDelta {
+        delta: StreamingDelta,
+    },
+    MessageDelta {
+        usage: MessagesUsage,
+    },
+    MessageStop,
+    Error {
+        error: StreamingError,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct StreamingMessage {
+    usage: MessagesUsage,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamingDelta {
+    TextDelta {
+        text: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct MessagesUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct StreamingError {
+    message: String,
+}
+
+#[async_trait]
+impl PromptModel for Anthropic {
+    fn supports_images(&self) -> bool {
+        true
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<Completion, Error> {
+        let settings = request.settings();
+        let model = settings.model.as_deref().unwrap_or(&self.model);
+        let mut messages = request.messages().to_vec();
+
+        let tools = request.tools();
+        let tool_definitions = tools
+            .iter()
+            .map(|tool| ToolDefinition {
+                name: tool.name(),
+                description: tool.description(),
+                input_schema: tool.parameters(),
+            })
+            .collect::<Vec<ToolDefinition>>();
+
+        //The Messages API can't be sent a schema, so we ask for it in the system message instead
+        let output_schema = request.output_schema();
+        let system_message = match output_schema {
+            Some(schema) => format!("{}\n\n{}", request.system_message(), schema.instructions()),
+            None => request.system_message(),
+        };
+
+        //Every round of the tool loop is billed, so we add up the usage from each of them
+        let mut usage = None;
+
+        for _ in 0..=MAX_TOOL_ITERATIONS {
+            let request_messages = to_request_messages(&messages);
+
+            // The Messages API has no seed or penalty parameters, so those settings are ignored
+            let messages_request = MessagesRequest {
+                model,
+                max_tokens: settings.max_tokens.unwrap_or(self.max_tokens),
+                temperature: settings.temperature,
+                top_p: settings.top_p,
+                stop_sequences: settings.stop.clone(),
+                //The system message goes in the top-level system field rather than in the messages
+                system: system_message.clone(),
+                messages: &request_messages,
+                tools: &tool_definitions,
+                stream: false,
+            };
+
+            let res = self
+                .send(&messages_request)
+                .await?
+                .json::<MessagesResponse>()
+                .await?;
+
+            usage = add_usage(
+                usage,
+                res.usage
+                    .map(|usage| Usage::new(usage.input_tokens, usage.output_tokens)),
+            );
+
+            let mut text = Vec::new();
+            let mut tool_calls = Vec::new();
+
+            for block in res.content {
+                match block {
+                    ContentBlock::Text { text: block } => text.push(block),
+                    ContentBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                        id,
+                        name,
+                        arguments: input,
+                    }),
+                    _ => {}
+                }
+            }
+
+            if tool_calls.is_empty() {
+                if text.is_empty() {
+                    return Err(Error::OptionIsNone);
+                }
+
+                let res = text.join("");
+
+                if let Some(schema) = output_schema {
+                    schema.validate(&res)?;
+                }
+
+                return Ok(Completion::new(res, model).with_usage(usage));
+            }
+
+            //The model wants to call tools, so we run them and feed the results back
+            let mut assistant = Message::new(Role::Assistant);
+
+            for block in &text {
+                assistant = assistant.with_text(block);
+            }
+
+            let mut results = Vec::new();
+
+            for tool_call in &tool_calls {
+                let content = call_tool(tools, &tool_call.name, tool_call.arguments.clone()).await;
+
+                results.push(Message::tool_result(&tool_call.id, &content));
+            }
+
+            messages.push(assistant.with_tool_calls(tool_calls));
+            messages.extend(results);
+        }
+
+        Err(Error::ToolLoopLimit(
+            request.agent().to_owned(),
+            MAX_TOOL_ITERATIONS,
+        ))
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<TokenStream, Error> {
+        //Tool calls and structured output need the whole response, so they don't get streamed
+        if !request.tools().is_empty() || request.output_schema().is_some() {
+            let res = self.chat(request).await?;
+
+            return Ok(single_response(res));
+        }
+
+        let settings = request.settings();
+        let messages = to_request_messages(request.messages());
+
+        let messages_request = MessagesRequest {
+            model: settings.model.as_deref().unwrap_or(&self.model),
+            max_tokens: settings.max_tokens.unwrap_or(self.max_tokens),
+            temperature: settings.temperature,
+            top_p: settings.top_p,
+            stop_sequences: settings.stop.clone(),
+            system: request.system_message(),
+            messages: &messages,
+            tools: &[],
+            stream: true,
+        };
+
+        let res = self.send(&messages_request).await?;
+        let model = messages_request.model.to_owned();
+
+        //The input tokens come with the first event and the output tokens with the last, so we
+        //keep a running total until the message stops
+        let stream = lines(res)
+            .scan(Usage::default(), move |usage, line| {
+                let event = match line {
+                    Ok(line) => match line.strip_prefix("data:") {
+                        Some(data) => {
+                            serde_json::from_str::<StreamingEvent>(data.trim()).map_err(Error::from)
+                        }
+                        None => Ok(StreamingEvent::Other),
+                    },
+                    Err(e) => Err(e),
+                };
+
+                let res = match event {
+                    Ok(StreamingEvent::MessageStart { message }) => {
+                        *usage =
+                            Usage::new(message.usage.input_tokens, message.usage.output_tokens);
+                        None
+                    }
+                    Ok(StreamingEvent::ContentBlockDelta {
+                        delta: StreamingDelta::TextDelta { text },
+                    }) => Some(Ok(StreamEvent::Delta(text))),
+                    Ok(StreamingEvent::MessageDelta { usage: delta }) => {
+                        *usage = Usage::new(usage.prompt_tokens, delta.output_tokens);
+                        None
+                    }
+                    Ok(StreamingEvent::MessageStop) => Some(Ok(StreamEvent::Done {
+                        model: model.to_owned(),
+                        usage: Some(*usage),
+                    })),
+                    Ok(StreamingEvent::Error { error }) => Some(Err(Error::ProviderError {
+                        status: 200,
+                        message: error.message,
+                    })),
+                    Ok(_) => None,
+                    Err(e) => Some(Err(e)),
+                };
+
+                futures::future::ready(Some(res))
+            })
+            .filter_map(futures::future::ready);
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Converts the messages into the Messages API's format. System messages are sent separately,
+/// and tool results are sent back as user messages.
+fn to_request_messages(messages: &[Message]) -> Vec<RequestMessage> {
+    let mut res: Vec<RequestMessage> = Vec::new();
+
+    for message in messages {
+        let (role, content) = match message.role() {
+            Role::System => continue,
+            //Anthropic recommends putting images before the text that refers to them
+            Role::User => {
+                let (images, text): (Vec<&ContentPart>, Vec<&ContentPart>) = message
+                    .content()
+                    .iter()
+                    .partition(|part| matches!(part, ContentPart::Image(_)));
+
+                let content = images
+                    .into_iter()
+                    .chain(text)
+                    .map(|part| match part {
+                        ContentPart::Text(text) => ContentBlock::Text {
+                            text: text.to_owned(),
+                        },
+                        ContentPart::Image(image) => ContentBlock::Image {
+                            source: ImageSource {
+                                kind: "base64".to_string(),
+                                media_type: image.media_type().to_owned(),
+                                data: image.data().to_owned(),
+                            },
+                        },
+                    })
+                    .collect::<Vec<ContentBlock>>();
+
+                ("user", content)
+            }
+            Role::Assistant => {
+                let mut content = Vec::new();
+
+                if !message.content().is_empty() {
+                    content.push(ContentBlock::Text {
+                        text: message.text(),
+                    });
+                }
+
+                content.extend(message.tool_calls().iter().map(|tool_call| {
+                    ContentBlock::ToolUse {
+                        id: tool_call.id.to_owned(),
+                        name: tool_call.name.to_owned(),
+                        input: tool_call.arguments.clone(),
+                    }
+                }));
+
+                ("assistant", content)
+            }
+            Role::Tool => (
+                "user",
+                vec![ContentBlock::ToolResult {
+                    tool_use_id: message.tool_call_id().unwrap_or_default().to_owned(),
+                    content: message.text(),
+                }],
+            ),
+        };
+
+        //The API wants the roles to alternate, so messages from the same role are merged
+        match res.last_mut() {
+            Some(last) if last.role == role => last.content.extend(content),
+            _ => res.push(RequestMessage { role, content }),
+        }
+    }
+
+    res
+}
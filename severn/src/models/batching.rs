@@ -0,0 +1,118 @@
+use std::future::Future;
+
+use crate::models::tokens::estimate_tokens;
+use crate::models::usage::add_usage;
+use crate::models::EmbeddingResponse;
+
+/// OpenAI accepts up to 2048 inputs per embedding request.
+pub const DEFAULT_MAX_BATCH_ITEMS: usize = 2048;
+/// OpenAI accepts up to 300,000 tokens per embedding request. Tokens are only estimated, so the
+/// default leaves plenty of headroom.
+pub const DEFAULT_MAX_BATCH_TOKENS: usize = 200_000;
+
+/// Limits on how much is sent in a single embedding request. Bigger inputs are split into several
+/// requests and the results are put back together in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchLimits {
+    max_items: usize,
+    max_tokens: usize,
+}
+
+impl Default for BatchLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BatchLimits {
+    pub fn new() -> Self {
+        Self {
+            max_items: DEFAULT_MAX_BATCH_ITEMS,
+            max_tokens: DEFAULT_MAX_BATCH_TOKENS,
+        }
+    }
+
+    pub fn with_max_items(mut self, max_items: usize) -> Self {
+        self.max_items = max_items.max(1);
+
+        self
+    }
+
+    pub fn max_items(&self) -> usize {
+        self.max_items
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+
+        self
+    }
+
+    pub fn max_tokens(&self) -> usize {
+        self.max_tokens
+    }
+
+    /// Splits the chunks into batches that fit within the limits, keeping them in order. A single
+    /// chunk that's over the token limit on its own gets a batch to itself.
+    pub fn split(&self, chunks: Vec<String>) -> Vec<Vec<String>> {
+        let mut batches = Vec::new();
+        let mut batch = Vec::new();
+        let mut batch_tokens = 0;
+
+        for chunk in chunks {
+            let tokens = estimate_tokens(&chunk);
+
+            if !batch.is_empty()
+                && (batch.len() >= self.max_items || batch_tokens + tokens > self.max_tokens)
+            {
+                batches.push(std::mem::take(&mut batch));
+                batch_tokens = 0;
+            }
+
+            batch_tokens += tokens;
+            batch.push(chunk);
+        }
+
+        if !batch.is_empty() {
+            batches.push(batch);
+        }
+
+        batches
+    }
+}
+
+/// Embeds the chunks one batch at a time, joining the embeddings back together in order and
+/// adding up the usage.
+pub(crate) async fn embed_in_batches<F, Fut>(
+    chunks: Vec<String>,
+    limits: &BatchLimits,
+    embed: F,
+) -> anyhow::Result<EmbeddingResponse>
+where
+    F: Fn(Vec<String>) -> Fut,
+    Fut: Future<Output = anyhow::Result<EmbeddingResponse>>,
+{
+    let mut res = EmbeddingResponse {
+        embeddings: Vec::new(),
+        model: String::new(),
+        usage: None,
+    };
+
+    for batch in limits.split(chunks) {
+        let expected = batch.len();
+        let batch_res = embed(batch).await?;
+
+        if batch_res.embeddings.len() != expected {
+            return Err(anyhow::anyhow!(
+                "Expected {expected} embeddings but got {}",
+                batch_res.embeddings.len()
+            ));
+        }
+
+        res.embeddings.extend(batch_res.embeddings);
+        res.model = batch_res.model;
+        res.usage = add_usage(res.usage, batch_res.usage);
+    }
+
+    Ok(res)
+}
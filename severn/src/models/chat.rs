@@ -0,0 +1,264 @@
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::agents::traits::Agent;
+use crate::models::{ContentPart, Image, ModelSettings};
+use crate::structured::OutputSchema;
+use crate::tools::Tool;
+
+/// Goes in front of the context that an agent is given, so the model can tell it apart from the
+/// prompt.
+pub(crate) const CONTEXT_PREFIX: &str = "Provided context:\n";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    /// The result of a tool call, sent back to the model.
+    Tool,
+}
+
+/// A tool that the model asked to be called.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// A single message in a conversation with a model.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    role: Role,
+    content: Vec<ContentPart>,
+    tool_calls: Vec<ToolCall>,
+    tool_call_id: Option<String>,
+}
+
+impl Message {
+    pub fn new(role: Role) -> Self {
+        Self {
+            role,
+            content: Vec::new(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+
+    pub fn system(text: &str) -> Self {
+        Self::new(Role::System).with_text(text)
+    }
+
+    pub fn user(text: &str) -> Self {
+        Self::new(Role::User).with_text(text)
+    }
+
+    pub fn assistant(text: &str) -> Self {
+        Self::new(Role::Assistant).with_text(text)
+    }
+
+    /// The result of the tool call with the id `tool_call_id`.
+    pub fn tool_result(tool_call_id: &str, content: &str) -> Self {
+        let mut message = Self::new(Role::Tool).with_text(content);
+        message.tool_call_id = Some(tool_call_id.to_owned());
+
+        message
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.content.push(ContentPart::Text(text.to_owned()));
+
+        self
+    }
+
+    pub fn with_image(mut self, image: Image) -> Self {
+        self.content.push(ContentPart::Image(image));
+
+        self
+    }
+
+    pub fn with_images(mut self, images: &[Image]) -> Self {
+        self.content
+            .extend(images.iter().cloned().map(ContentPart::Image));
+
+        self
+    }
+
+    /// Adds `data` as the context for the prompt.
+    pub fn with_context(self, data: &str) -> Self {
+        self.with_text(&format!("{CONTEXT_PREFIX}{data}"))
+    }
+
+    /// The tools that the model asked for. Only used on assistant messages.
+    pub fn with_tool_calls(mut self, tool_calls: Vec<ToolCall>) -> Self {
+        self.tool_calls = tool_calls;
+
+        self
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn content(&self) -> &[ContentPart] {
+        &self.content
+    }
+
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        &self.tool_calls
+    }
+
+    pub fn tool_call_id(&self) -> Option<&str> {
+        self.tool_call_id.as_deref()
+    }
+
+    /// Every text part of the message, joined by blank lines.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text(text) => Some(text.as_str()),
+                ContentPart::Image(_) => None,
+            })
+            .collect::<Vec<&str>>()
+            .join("\n\n")
+    }
+
+    pub fn images(&self) -> Vec<&Image> {
+        self.content
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Image(image) => Some(image),
+                ContentPart::Text(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Everything sent to a model for a single answer: the conversation so far, the settings, and the
+/// tools and output schema the answer can use.
+#[derive(Clone, Default)]
+pub struct ChatRequest {
+    agent: String,
+    messages: Vec<Message>,
+    settings: ModelSettings,
+    tools: Vec<Arc<dyn Tool>>,
+    output_schema: Option<OutputSchema>,
+}
+
+impl ChatRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The request an agent makes for a prompt: its system message, then the prompt with the
+    /// context, using the agent's settings, tools and output schema.
+    pub fn for_agent(agent: &Arc<dyn Agent>, prompt: &str, data: &str) -> Self {
+        Self::new()
+            .with_agent(&agent.name())
+            .with_message(Message::system(&agent.system_message()))
+            .with_message(Message::user(prompt).with_context(data))
+            .with_settings(agent.settings())
+            .with_tools(agent.tools())
+            .with_output_schema(agent.output_schema())
+    }
+
+    /// The name of the agent making the request, used in errors and by `MockModel`.
+    pub fn with_agent(mut self, agent: &str) -> Self {
+        self.agent = agent.to_owned();
+
+        self
+    }
+
+    pub fn agent(&self) -> &str {
+        &self.agent
+    }
+
+    pub fn with_message(mut self, message: Message) -> Self {
+        self.messages.push(message);
+
+        self
+    }
+
+    /// Replaces any system messages with `text`, as the first message.
+    pub fn with_system_message(mut self, text: &str) -> Self {
+        self.messages.retain(|message| message.role != Role::System);
+        self.messages.insert(0, Message::system(text));
+
+        self
+    }
+
+    pub fn with_messages(mut self, messages: Vec<Message>) -> Self {
+        self.messages.extend(messages);
+
+        self
+    }
+
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+
+    pub fn settings(&self) -> &ModelSettings {
+        &self.settings
+    }
+
+    pub fn with_tools(mut self, tools: Vec<Arc<dyn Tool>>) -> Self {
+        self.tools = tools;
+
+        self
+    }
+
+    pub fn tools(&self) -> &[Arc<dyn Tool>] {
+        &self.tools
+    }
+
+    pub fn with_output_schema(mut self, output_schema: Option<OutputSchema>) -> Self {
+        self.output_schema = output_schema;
+
+        self
+    }
+
+    pub fn output_schema(&self) -> Option<&OutputSchema> {
+        self.output_schema.as_ref()
+    }
+
+    /// Adds `images` to the last user message.
+    pub fn with_images(mut self, images: &[Image]) -> Self {
+        if let Some(message) = self
+            .messages
+            .iter_mut()
+            .rev()
+            .find(|message| message.role == Role::User)
+        {
+            message
+                .content
+                .extend(images.iter().cloned().map(ContentPart::Image));
+        }
+
+        self
+    }
+
+    pub fn has_images(&self) -> bool {
+        self.messages
+            .iter()
+            .any(|message| !message.images().is_empty())
+    }
+
+    /// The system messages joined together, for providers that take the system prompt separately.
+    pub(crate) fn system_message(&self) -> String {
+        self.messages
+            .iter()
+            .filter(|message| message.role == Role::System)
+            .map(|message| message.text())
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::errors::Error;
+
+/// An image that can be sent to a model alongside the prompt, stored as base64.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    media_type: String,
+    data: String,
+}
+
+impl Image {
+    pub fn from_bytes(bytes: &[u8], media_type: &str) -> Self {
+        Self {
+            media_type: media_type.to_owned(),
+            data: STANDARD.encode(bytes),
+        }
+    }
+
+    /// Creates an image from data that's already base64 encoded.
+    pub fn from_base64(data: &str, media_type: &str) -> Self {
+        Self {
+            media_type: media_type.to_owned(),
+            data: data.to_owned(),
+        }
+    }
+
+    /// Reads an image from a local file. The media type is worked out from the file extension,
+    /// which has to be one of png, jpg, jpeg, gif or webp.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+
+        let media_type = match extension.as_deref() {
+            Some("png") => "image/png",
+            Some("jpg" | "jpeg") => "image/jpeg",
+            Some("gif") => "image/gif",
+            Some("webp") => "image/webp",
+            _ => {
+                return Err(Error::InvalidImage(format!(
+                    "can't tell the image type of {}",
+                    path.display()
+                )))
+            }
+        };
+
+        Ok(Self::from_bytes(&std::fs::read(path)?, media_type))
+    }
+
+    /// Parses a base64 data URL, like `data:image/png;base64,iVBORw0...`.
+    pub fn from_data_url(url: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidImage("expected a base64 data URL".to_string());
+
+        let (header, data) = url
+            .strip_prefix("data:")
+            .and_then(|url| url.split_once(','))
+            .ok_or_else(invalid)?;
+
+        let media_type = header.strip_suffix(";base64").ok_or_else(invalid)?;
+
+        Ok(Self::from_base64(data, media_type))
+    }
+
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    /// The image, base64 encoded.
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.media_type, self.data)
+    }
+}
+
+/// A piece of a message: some text or an image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ContentPart {
+    Text(String),
+    Image(Image),
+}
+
+impl From<&str> for ContentPart {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_owned())
+    }
+}
+
+impl From<String> for ContentPart {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<Image> for ContentPart {
+    fn from(image: Image) -> Self {
+        Self::Image(image)
+    }
+}
@@ -0,0 +1,245 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+
+use crate::models::batching::embed_in_batches;
+use crate::models::{BatchLimits, EmbedModel, EmbeddingResponse};
+
+/// How many chunks are run through the model at once by default. Every chunk in a batch is padded
+/// to the longest one, so big batches use a lot of memory on CPU.
+pub const DEFAULT_LOCAL_BATCH_SIZE: usize = 32;
+
+/// The most tokens a chunk can have before it's truncated, which is the limit for most BERT
+/// models.
+pub const DEFAULT_MAX_SEQUENCE_LENGTH: usize = 512;
+
+/// How the per-token outputs of the model are turned into a single embedding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pooling {
+    /// Average every token, ignoring padding. This is what most sentence-transformers use.
+    Mean,
+    /// Use the output for the first (`[CLS]`) token.
+    Cls,
+}
+
+/// An `EmbedModel` that runs a BERT-style sentence-transformer (for example `all-MiniLM-L6-v2`)
+/// on the CPU, so nothing leaves the machine and no API key is needed.
+///
+/// The model is loaded from a local directory containing `config.json`, `tokenizer.json` and
+/// `model.safetensors`, as downloaded from the Hugging Face hub.
+#[derive(Clone)]
+pub struct LocalEmbedder {
+    inner: Arc<Inner>,
+    name: String,
+    pooling: Pooling,
+    normalize: bool,
+    batch_limits: BatchLimits,
+}
+
+struct Inner {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl LocalEmbedder {
+    /// Loads the model from a directory with `config.json`, `tokenizer.json` and
+    /// `model.safetensors` in it.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        Self::from_files(
+            path.join("config.json"),
+            path.join("tokenizer.json"),
+            path.join("model.safetensors"),
+        )
+    }
+
+    pub fn from_files<P: AsRef<Path>>(config: P, tokenizer: P, weights: P) -> Result<Self> {
+        let device = Device::Cpu;
+
+        let config: Config = serde_json::from_str(&std::fs::read_to_string(config)?)?;
+
+        let tensors = candle_core::safetensors::load(weights, &device)?;
+        let model = BertModel::load(VarBuilder::from_tensors(tensors, DTYPE, &device), &config)?;
+
+        let mut tokenizer = Tokenizer::from_file(tokenizer).map_err(anyhow::Error::msg)?;
+
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            ..Default::default()
+        }));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: DEFAULT_MAX_SEQUENCE_LENGTH,
+                ..Default::default()
+            }))
+            .map_err(anyhow::Error::msg)?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                model,
+                tokenizer,
+                device,
+            }),
+            name: String::from("local"),
+            pooling: Pooling::Mean,
+            normalize: true,
+            batch_limits: BatchLimits::new().with_max_items(DEFAULT_LOCAL_BATCH_SIZE),
+        })
+    }
+
+    /// Sets the name reported as the model in `EmbeddingResponse`. Defaults to `local`.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn with_pooling(mut self, pooling: Pooling) -> Self {
+        self.pooling = pooling;
+
+        self
+    }
+
+    pub fn pooling(&self) -> Pooling {
+        self.pooling
+    }
+
+    /// Whether embeddings are scaled to a length of 1. On by default, so that a dot product gives
+    /// the cosine similarity.
+    pub fn with_normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+
+        self
+    }
+
+    pub fn normalize(&self) -> bool {
+        self.normalize
+    }
+
+    pub fn with_batch_limits(mut self, batch_limits: BatchLimits) -> Self {
+        self.batch_limits = batch_limits;
+
+        self
+    }
+
+    async fn embed(&self, chunks: Vec<String>) -> Result<EmbeddingResponse> {
+        let inner = self.inner.clone();
+        let (pooling, normalize) = (self.pooling, self.normalize);
+
+        //Running the model is CPU-bound, so it's kept off the async runtime's worker threads
+        let embeddings =
+            tokio::task::spawn_blocking(move || inner.embed(chunks, pooling, normalize)).await??;
+
+        Ok(EmbeddingResponse {
+            embeddings,
+            model: self.name.to_owned(),
+            usage: None,
+        })
+    }
+}
+
+impl Inner {
+    fn embed(
+        &self,
+        chunks: Vec<String>,
+        pooling: Pooling,
+        normalize: bool,
+    ) -> Result<Vec<Vec<f32>>> {
+        if chunks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let encodings = self
+            .tokenizer
+            .encode_batch(chunks, true)
+            .map_err(anyhow::Error::msg)?;
+
+        let input_ids = encodings
+            .iter()
+            .map(|encoding| Tensor::new(encoding.get_ids(), &self.device))
+            .collect::<candle_core::Result<Vec<Tensor>>>()?;
+        let attention_mask = encodings
+            .iter()
+            .map(|encoding| Tensor::new(encoding.get_attention_mask(), &self.device))
+            .collect::<candle_core::Result<Vec<Tensor>>>()?;
+
+        let input_ids = Tensor::stack(&input_ids, 0)?;
+        let attention_mask = Tensor::stack(&attention_mask, 0)?;
+        let token_type_ids = input_ids.zeros_like()?;
+
+        //The output is (batch, tokens, hidden size)
+        let output = self
+            .model
+            .forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+
+        let pooled = match pooling {
+            Pooling::Mean => {
+                //Padding tokens have a mask of 0, so they don't count towards the average
+                let mask = attention_mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+                let summed = output.broadcast_mul(&mask)?.sum(1)?;
+
+                summed.broadcast_div(&mask.sum(1)?)?
+            }
+            Pooling::Cls => output.narrow(1, 0, 1)?.squeeze(1)?,
+        };
+
+        let pooled = if normalize {
+            let norm = pooled.sqr()?.sum_keepdim(1)?.sqrt()?;
+
+            pooled.broadcast_div(&norm)?
+        } else {
+            pooled
+        };
+
+        Ok(pooled.to_vec2::<f32>()?)
+    }
+}
+
+#[async_trait]
+impl EmbedModel for LocalEmbedder {
+    fn batch_limits(&self) -> BatchLimits {
+        self.batch_limits
+    }
+
+    async fn embed_file(&self, chunked_contents: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let res = self.embed_file_with_usage(chunked_contents).await?;
+
+        Ok(res.embeddings)
+    }
+
+    async fn embed_file_with_usage(
+        &self,
+        chunked_contents: Vec<String>,
+    ) -> Result<EmbeddingResponse> {
+        embed_in_batches(chunked_contents, &self.batch_limits, |batch| {
+            self.embed(batch)
+        })
+        .await
+    }
+
+    async fn embed_sentence(&self, prompt: &str) -> Result<Vec<f32>> {
+        let embedding = self
+            .embed(vec![prompt.to_owned()])
+            .await?
+            .embeddings
+            .into_iter()
+            .next();
+
+        match embedding {
+            Some(res) => Ok(res),
+            None => Err(anyhow::anyhow!("The model didn't return an embedding!")),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::errors::Error;
+use crate::models::chat::CONTEXT_PREFIX;
+use crate::models::{
+    ChatRequest, Completion, ContentPart, EmbedModel, Image, Message, ModelSettings, PromptModel,
+    Role, Usage,
+};
+
+pub const DEFAULT_MOCK_DIMENSIONS: usize = 8;
+pub const MOCK_MODEL_NAME: &str = "mock";
+
+/// A call that a `MockModel` received.
+///
+/// `prompt`, `data` and `images` are taken from the last user message, with the context given
+/// to an agent in `data`. `messages` has everything that was sent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockCall {
+    pub agent: String,
+    pub prompt: String,
+    pub data: String,
+    pub images: Vec<Image>,
+    pub settings: ModelSettings,
+    pub messages: Vec<Message>,
+}
+
+impl MockCall {
+    fn new(request: &ChatRequest) -> Self {
+        let mut prompt = Vec::new();
+        let mut data = Vec::new();
+        let mut images = Vec::new();
+
+        let last_user = request
+            .messages()
+            .iter()
+            .rev()
+            .find(|message| message.role() == Role::User);
+
+        for part in last_user
+            .map(|message| message.content())
+            .unwrap_or_default()
+        {
+            match part {
+                ContentPart::Text(text) => match text.strip_prefix(CONTEXT_PREFIX) {
+                    Some(context) => data.push(context.to_owned()),
+                    None => prompt.push(text.to_owned()),
+                },
+                ContentPart::Image(image) => images.push(image.clone()),
+            }
+        }
+
+        Self {
+            agent: request.agent().to_owned(),
+            prompt: prompt.join("\n\n"),
+            data: data.join("\n\n"),
+            images,
+            settings: request.settings().to_owned(),
+            messages: request.messages().to_vec(),
+        }
+    }
+}
+
+/// A model that returns canned responses, for testing pipelines and agents without a provider.
+///
+/// Responses are picked in this order: a response set for the agent's name, then the first
+/// response whose substring appears in the prompt or its context, then the next queued response,
+/// then the default response. If none of those match, the call fails with
+/// `Error::NoMockResponse`.
+///
+/// Embeddings are generated from a hash of the text, so the same text always gets the same vector.
+#[derive(Debug)]
+pub struct MockModel {
+    agent_responses: HashMap<String, String>,
+    prompt_responses: Vec<(String, String)>,
+    queue: Mutex<VecDeque<String>>,
+    default_response: Option<String>,
+    usage: Option<Usage>,
+    dimensions: usize,
+    calls: Mutex<Vec<MockCall>>,
+    embedded: Mutex<Vec<String>>,
+}
+
+impl Default for MockModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockModel {
+    pub fn new() -> Self {
+        Self {
+            agent_responses: HashMap::new(),
+            prompt_responses: Vec::new(),
+            queue: Mutex::new(VecDeque::new()),
+            default_response: None,
+            usage: None,
+            dimensions: DEFAULT_MOCK_DIMENSIONS,
+            calls: Mutex::new(Vec::new()),
+            embedded: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Responds with `response` whenever the agent called `agent` is prompted.
+    pub fn with_agent_response(mut self, agent: &str, response: &str) -> Self {
+        self.agent_responses
+            .insert(agent.to_owned(), response.to_owned());
+
+        self
+    }
+
+    /// Responds with `response` whenever the prompt or its context contains `substring`.
+    pub fn with_prompt_response(mut self, substring: &str, response: &str) -> Self {
+        self.prompt_responses
+            .push((substring.to_owned(), response.to_owned()));
+
+        self
+    }
+
+    /// Adds a response to the back of the queue. Each queued response is only used once.
+    pub fn with_queued_response(self, response: &str) -> Self {
+        self.queue_response(response);
+
+        self
+    }
+
+    pub fn with_default_response(mut self, response: &str) -> Self {
+        self.default_response = Some(response.to_owned());
+
+        self
+    }
+
+    /// Reports `usage` for every prompt. Completions are reported as coming from the model set in
+    /// the settings, or `mock` if there isn't one.
+    pub fn with_usage(mut self, usage: Usage) -> Self {
+        self.usage = Some(usage);
+
+        self
+    }
+
+    /// Sets the length of the vectors returned by the embedding methods.
+    pub fn with_dimensions(mut self, dimensions: usize) -> Self {
+        self.dimensions = dimensions;
+
+        self
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// Adds a response to the back of the queue after the model has been shared.
+    pub fn queue_response(&self, response: &str) {
+        self.queue.lock().unwrap().push_back(response.to_owned());
+    }
+
+    /// Every prompt the model has received, in order.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// The prompts received for a single agent, in order.
+    pub fn calls_for(&self, agent: &str) -> Vec<MockCall> {
+        self.calls()
+            .into_iter()
+            .filter(|call| call.agent == agent)
+            .collect()
+    }
+
+    /// Every piece of text the model has embedded, in order.
+    pub fn embedded(&self) -> Vec<String> {
+        self.embedded.lock().unwrap().clone()
+    }
+
+    fn respond(&self, agent: &str, prompt: &str, data: &str) -> Result<String, Error> {
+        if let Some(res) = self.agent_responses.get(agent) {
+            return Ok(res.to_owned());
+        }
+
+        let by_prompt = self
+            .prompt_responses
+            .iter()
+            .find(|(substring, _)| prompt.contains(substring) || data.contains(substring));
+
+        if let Some((_, res)) = by_prompt {
+            return Ok(res.to_owned());
+        }
+
+        if let Some(res) = self.queue.lock().unwrap().pop_front() {
+            return Ok(res);
+        }
+
+        self.default_response
+            .clone()
+            .ok_or_else(|| Error::NoMockResponse(agent.to_owned()))
+    }
+
+    fn embedding(&self, text: &str) -> Vec<f32> {
+        self.embedded.lock().unwrap().push(text.to_owned());
+
+        let vector = (0..self.dimensions)
+            .map(|idx| {
+                let hash = fnv1a(text.as_bytes(), idx as u64);
+
+                //Map the hash onto -1.0..=1.0
+                (hash as f64 / u64::MAX as f64 * 2.0 - 1.0) as f32
+            })
+            .collect::<Vec<f32>>();
+
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+        if norm == 0.0 {
+            return vector;
+        }
+
+        vector.into_iter().map(|x| x / norm).collect()
+    }
+}
+
+#[async_trait]
+impl PromptModel for MockModel {
+    fn supports_images(&self) -> bool {
+        true
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<Completion, Error> {
+        let call = MockCall::new(request);
+        self.calls.lock().unwrap().push(call.clone());
+
+        let res = self.respond(&call.agent, &call.prompt, &call.data)?;
+
+        let model = request
+            .settings()
+            .model
+            .as_deref()
+            .unwrap_or(MOCK_MODEL_NAME);
+
+        Ok(Completion::new(res, model).with_usage(self.usage))
+    }
+}
+
+#[async_trait]
+impl EmbedModel for MockModel {
+    async fn embed_file(&self, chunked_contents: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        Ok(chunked_contents
+            .iter()
+            .map(|chunk| self.embedding(chunk))
+            .collect())
+    }
+
+    async fn embed_sentence(&self, prompt: &str) -> anyhow::Result<Vec<f32>> {
+        Ok(self.embedding(prompt))
+    }
+}
+
+// FNV-1a, seeded so that every dimension gets a different hash. Unlike `DefaultHasher`, it's
+// guaranteed to give the same result across Rust releases.
+fn fnv1a(bytes: &[u8], seed: u64) -> u64 {
+    let mut hash = 0xcbf29ce484222325 ^ seed.wrapping_mul(0x9e3779b97f4a7c15);
+
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
@@ -0,0 +1,236 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::agents::traits::Agent;
+use async_trait::async_trait;
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+
+use crate::errors::Error;
+
+pub mod anthropic;
+pub mod batching;
+pub mod chat;
+pub mod content;
+#[cfg(feature = "local-embeddings")]
+pub mod local;
+pub mod mock;
+pub mod ollama;
+pub mod openai;
+pub mod settings;
+pub mod streaming;
+pub mod tokens;
+pub mod usage;
+
+pub use anthropic::Anthropic;
+pub use batching::BatchLimits;
+pub use chat::{ChatRequest, Message, Role, ToolCall};
+pub use content::{ContentPart, Image};
+#[cfg(feature = "local-embeddings")]
+pub use local::{LocalEmbedder, Pooling};
+pub use mock::{MockCall, MockModel};
+pub use ollama::Ollama;
+pub use openai::{OpenAI, OpenAICompatibleConfig};
+pub use settings::ModelSettings;
+pub use streaming::{StreamEvent, TokenStream};
+pub use tokens::estimate_tokens;
+pub use usage::{
+    Completion, EmbeddingResponse, ModelPrice, PriceTable, Usage, UsageReport, UsageTotals,
+};
+
+use streaming::single_response;
+
+#[async_trait]
+pub trait PromptModel: Send + Sync {
+    /// Sends the conversation to the model, running any tools it asks for, and returns its answer
+    /// along with the tokens it used.
+    async fn chat(&self, request: &ChatRequest) -> Result<Completion, Error>;
+
+    /// Whether the model can be sent images. Requests with images are turned away with
+    /// `Error::ImagesNotSupported` before they reach a model that returns `false`.
+    fn supports_images(&self) -> bool {
+        false
+    }
+
+    /// Streams the model's answer as it's generated.
+    ///
+    /// Models that can't stream return the whole answer as a single delta.
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<TokenStream, Error> {
+        let res = self.chat(request).await?;
+
+        Ok(single_response(res))
+    }
+
+    /// Prompts the model using the agent's own settings.
+    async fn prompt(
+        &self,
+        prompt: &str,
+        data: String,
+        agent: &Arc<dyn Agent>,
+    ) -> Result<String, Error> {
+        let res = self
+            .prompt_with_settings(prompt, data, agent, &agent.settings())
+            .await?;
+
+        Ok(res.content)
+    }
+
+    /// Prompts the model, returning the answer along with the tokens it used.
+    async fn prompt_with_settings(
+        &self,
+        prompt: &str,
+        data: String,
+        agent: &Arc<dyn Agent>,
+        settings: &ModelSettings,
+    ) -> Result<Completion, Error> {
+        let request = ChatRequest::for_agent(agent, prompt, &data).with_settings(settings.clone());
+
+        self.chat(&request).await
+    }
+
+    /// Prompts the model with images attached to the prompt.
+    async fn prompt_with_images(
+        &self,
+        prompt: &str,
+        images: &[Image],
+        data: String,
+        agent: &Arc<dyn Agent>,
+        settings: &ModelSettings,
+    ) -> Result<Completion, Error> {
+        let request = ChatRequest::for_agent(agent, prompt, &data)
+            .with_settings(settings.clone())
+            .with_images(images);
+
+        if request.has_images() && !self.supports_images() {
+            return Err(Error::ImagesNotSupported(agent.name()));
+        }
+
+        self.chat(&request).await
+    }
+
+    /// Streams the model's answer to a prompt as it's generated.
+    async fn prompt_stream(
+        &self,
+        prompt: &str,
+        data: String,
+        agent: &Arc<dyn Agent>,
+        settings: &ModelSettings,
+    ) -> Result<TokenStream, Error> {
+        let request = ChatRequest::for_agent(agent, prompt, &data).with_settings(settings.clone());
+
+        self.chat_stream(&request).await
+    }
+}
+
+#[async_trait]
+impl<P: PromptModel + ?Sized> PromptModel for Arc<P> {
+    async fn chat(&self, request: &ChatRequest) -> Result<Completion, Error> {
+        (**self).chat(request).await
+    }
+
+    fn supports_images(&self) -> bool {
+        (**self).supports_images()
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<TokenStream, Error> {
+        (**self).chat_stream(request).await
+    }
+
+    async fn prompt_with_settings(
+        &self,
+        prompt: &str,
+        data: String,
+        agent: &Arc<dyn Agent>,
+        settings: &ModelSettings,
+    ) -> Result<Completion, Error> {
+        (**self)
+            .prompt_with_settings(prompt, data, agent, settings)
+            .await
+    }
+
+    async fn prompt_with_images(
+        &self,
+        prompt: &str,
+        images: &[Image],
+        data: String,
+        agent: &Arc<dyn Agent>,
+        settings: &ModelSettings,
+    ) -> Result<Completion, Error> {
+        (**self)
+            .prompt_with_images(prompt, images, data, agent, settings)
+            .await
+    }
+
+    async fn prompt_stream(
+        &self,
+        prompt: &str,
+        data: String,
+        agent: &Arc<dyn Agent>,
+        settings: &ModelSettings,
+    ) -> Result<TokenStream, Error> {
+        (**self).prompt_stream(prompt, data, agent, settings).await
+    }
+}
+
+#[async_trait]
+pub trait EmbedModel: Send + Sync {
+    /// How `embed_file` splits its input into requests. Embedders that don't batch send each
+    /// default-sized batch on its own.
+    fn batch_limits(&self) -> BatchLimits {
+        BatchLimits::new()
+    }
+
+    async fn embed_file(&self, chunked_contents: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>>;
+    async fn embed_sentence(&self, prompt: &str) -> anyhow::Result<Vec<f32>>;
+
+    /// Embeds the chunks, returning the tokens used along with the embeddings.
+    ///
+    /// Embedders that don't report usage return the embeddings on their own.
+    async fn embed_file_with_usage(
+        &self,
+        chunked_contents: Vec<String>,
+    ) -> anyhow::Result<EmbeddingResponse> {
+        Ok(EmbeddingResponse {
+            embeddings: self.embed_file(chunked_contents).await?,
+            model: String::new(),
+            usage: None,
+        })
+    }
+}
+
+/// Turns a non-success response from a provider's HTTP API into an `Error`, picking up any
+/// `Retry-After` hint on a 429.
+pub(crate) async fn error_for_status(res: Response) -> Result<Response, Error> {
+    let status = res.status();
+
+    if status.is_success() {
+        return Ok(res);
+    }
+
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(Duration::from_secs);
+
+        return Err(Error::RateLimited { retry_after });
+    }
+
+    let body = res.text().await.unwrap_or_default();
+
+    // Providers either nest the message (`{"error": {"message": ...}}`) or give it directly
+    // (`{"error": "..."}`)
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|value| match &value["error"] {
+            serde_json::Value::String(message) => Some(message.to_owned()),
+            error => error["message"].as_str().map(|x| x.to_owned()),
+        })
+        .unwrap_or(body);
+
+    Err(Error::ProviderError {
+        status: status.as_u16(),
+        message,
+    })
+}
@@ -0,0 +1,497 @@
+use crate::models::batching::embed_in_batches;
+use crate::models::streaming::{lines, single_response};
+use crate::models::usage::add_usage;
+use crate::models::{
+    error_for_status, BatchLimits, ChatRequest, Completion, EmbedModel, EmbeddingResponse, Message,
+    ModelSettings, PromptModel, Role, StreamEvent, TokenStream, Usage,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::errors::Error;
+use crate::tools::{call_tool, MAX_TOOL_ITERATIONS};
+
+pub const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+
+/// A `PromptModel` and `EmbedModel` backed by a local Ollama server, so nothing leaves the
+/// machine.
+#[derive(Clone)]
+pub struct Ollama {
+    http: Client,
+    base_url: String,
+    model: String,
+    embedding_model: String,
+    batch_limits: BatchLimits,
+}
+
+impl Ollama {
+    pub fn new(model: &str) -> Self {
+        Self {
+            http: Client::new(),
+            base_url: OLLAMA_BASE_URL.to_string(),
+            model: model.to_owned(),
+            embedding_model: "nomic-embed-text".to_string(),
+            batch_limits: BatchLimits::new(),
+        }
+    }
+
+    /// Uses the `OLLAMA_HOST` environment variable as the base URL if it's set.
+    pub fn from_env(model: &str) -> Self {
+        match std::env::var("OLLAMA_HOST") {
+            Ok(host) => Self::new(model).with_base_url(&host),
+            Err(_) => Self::new(model),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_owned();
+
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_owned();
+
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn with_embedding_model(mut self, embedding_model: &str) -> Self {
+        self.embedding_model = embedding_model.to_owned();
+
+        self
+    }
+
+    pub fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    pub fn with_batch_limits(mut self, batch_limits: BatchLimits) -> Self {
+        self.batch_limits = batch_limits;
+
+        self
+    }
+
+    pub fn with_http_client(mut self, http: Client) -> Self {
+        self.http = http;
+
+        self
+    }
+
+    async fn embed(&self, input: Vec<String>) -> Result<EmbeddingResponse, Error> {
+        let request = EmbedRequest {
+            model: &self.embedding_model,
+            input,
+        };
+
+        let res = self
+            .http
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&request)
+            .send()
+            .await?;
+
+        let res = error_for_status(res).await?.json::<EmbedResponse>().await?;
+
+        Ok(EmbeddingResponse {
+            embeddings: res.embeddings,
+            model: self.embedding_model.to_owned(),
+            usage: res.prompt_eval_count.map(|tokens| Usage::new(tokens, 0)),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct ChatBody<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    stream: bool,
+    options: &'a ChatOptions,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    tools: &'a [ToolDefinition],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<&'a Value>,
+}
+
+#[derive(Serialize)]
+struct ToolDefinition {
+    r#type: &'static str,
+    function: FunctionDefinition,
+}
+
+#[derive(Serialize)]
+struct FunctionDefinition {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+impl From<&ModelSettings> for ChatOptions {
+    fn from(settings: &ModelSettings) -> Self {
+        Self {
+            temperature: settings.temperature,
+            top_p: settings.top_p,
+            num_predict: settings.max_tokens,
+            stop: settings.stop.clone(),
+            seed: settings.seed,
+            presence_penalty: settings.presence_penalty,
+            frequency_penalty: settings.frequency_penalty,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChatOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChatMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<ToolCall>,
+    //Ollama takes images as plain base64, without the data URL prefix
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    //Tool results say which tool they came from, since Ollama's tool calls have no ids
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
+}
+
+impl ChatMessage {
+    fn new(role: &str, content: String) -> Self {
+        Self {
+            role: role.to_owned(),
+            content,
+            tool_calls: Vec::new(),
+            images: Vec::new(),
+            tool_name: None,
+        }
+    }
+
+    fn tool_result(tool_name: &str, content: String) -> Self {
+        Self {
+            tool_name: Some(tool_name.to_owned()),
+            ..Self::new("tool", content)
+        }
+    }
+}
+
+/// Converts the messages into Ollama's format, naming the tool each tool result came from by
+/// finding the call it answers.
+fn to_chat_messages(messages: &[Message]) -> Vec<ChatMessage> {
+    messages
+        .iter()
+        .map(|message| {
+            let mut res = ChatMessage::from(message);
+
+            if let Some(id) = message.tool_call_id() {
+                res.tool_name = messages
+                    .iter()
+                    .flat_map(|message| message.tool_calls())
+                    .find(|tool_call| tool_call.id == id)
+                    .map(|tool_call| tool_call.name.to_owned());
+            }
+
+            res
+        })
+        .collect()
+}
+
+impl From<&Message> for ChatMessage {
+    fn from(message: &Message) -> Self {
+        let role = match message.role() {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+        };
+
+        let mut res = ChatMessage::new(role, message.text());
+
+        res.images = message
+            .images()
+            .iter()
+            .map(|image| image.data().to_owned())
+            .collect();
+        res.tool_calls = message
+            .tool_calls()
+            .iter()
+            .map(|tool_call| ToolCall {
+                function: FunctionCall {
+                    name: tool_call.name.to_owned(),
+                    arguments: tool_call.arguments.clone(),
+                },
+            })
+            .collect();
+
+        res
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ToolCall {
+    function: FunctionCall,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct FunctionCall {
+    name: String,
+    arguments: Value,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: ChatMessage,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ChatStreamChunk {
+    #[serde(default)]
+    model: String,
+    message: Option<ChatMessage>,
+    #[serde(default)]
+    done: bool,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    input: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+    prompt_eval_count: Option<u32>,
+}
+
+#[async_trait]
+impl PromptModel for Ollama {
+    fn supports_images(&self) -> bool {
+        true
+    }
+
+    /// Images are sent with their message. The model has to support vision, like `llava`.
+    async fn chat(&self, request: &ChatRequest) -> Result<Completion, Error> {
+        let settings = request.settings();
+        let model = settings.model.as_deref().unwrap_or(&self.model);
+        let mut messages = to_chat_messages(request.messages());
+
+        let options = ChatOptions::from(settings);
+
+        let tools = request.tools();
+        let tool_definitions = tools
+            .iter()
+            .map(|tool| ToolDefinition {
+                r#type: "function",
+                function: FunctionDefinition {
+                    name: tool.name(),
+                    description: tool.description(),
+                    parameters: tool.parameters(),
+                },
+            })
+            .collect::<Vec<ToolDefinition>>();
+
+        let output_schema = request.output_schema();
+
+        //Every round of the tool loop uses tokens, so we add up the usage from each of them
+        let mut usage = None;
+
+        for _ in 0..=MAX_TOOL_ITERATIONS {
+            let body = ChatBody {
+                model,
+                messages: &messages,
+                stream: false,
+                options: &options,
+                tools: &tool_definitions,
+                format: output_schema.map(|schema| schema.schema()),
+            };
+
+            let res = self
+                .http
+                .post(format!("{}/api/chat", self.base_url))
+                .json(&body)
+                .send()
+                .await?;
+
+            let res = error_for_status(res).await?.json::<ChatResponse>().await?;
+
+            if let (Some(prompt_tokens), Some(completion_tokens)) =
+                (res.prompt_eval_count, res.eval_count)
+            {
+                usage = add_usage(usage, Some(Usage::new(prompt_tokens, completion_tokens)));
+            }
+
+            let message = res.message;
+
+            if message.tool_calls.is_empty() {
+                let res = message.content;
+
+                if let Some(schema) = output_schema {
+                    schema.validate(&res)?;
+                }
+
+                return Ok(Completion::new(res, model).with_usage(usage));
+            }
+
+            //The model wants to call tools, so we run them and feed the results back
+            let tool_calls = message.tool_calls.clone();
+            messages.push(message);
+
+            for tool_call in tool_calls {
+                let content = call_tool(
+                    tools,
+                    &tool_call.function.name,
+                    tool_call.function.arguments,
+                )
+                .await;
+
+                messages.push(ChatMessage::tool_result(&tool_call.function.name, content));
+            }
+        }
+
+        Err(Error::ToolLoopLimit(
+            request.agent().to_owned(),
+            MAX_TOOL_ITERATIONS,
+        ))
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<TokenStream, Error> {
+        //Tool calls and structured output need the whole response, so they don't get streamed
+        if !request.tools().is_empty() || request.output_schema().is_some() {
+            let res = self.chat(request).await?;
+
+            return Ok(single_response(res));
+        }
+
+        let settings = request.settings();
+        let messages = to_chat_messages(request.messages());
+
+        let body = ChatBody {
+            model: settings.model.as_deref().unwrap_or(&self.model),
+            messages: &messages,
+            stream: true,
+            options: &ChatOptions::from(settings),
+            tools: &[],
+            format: None,
+        };
+
+        let res = self
+            .http
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&body)
+            .send()
+            .await?;
+
+        //Ollama streams one JSON object per line, and the last one has the token counts
+        let stream = lines(error_for_status(res).await?).filter_map(|line| async move {
+            let line = match line {
+                Ok(line) if line.trim().is_empty() => return None,
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let chunk = match serde_json::from_str::<ChatStreamChunk>(&line) {
+                Ok(chunk) => chunk,
+                Err(e) => return Some(Err(e.into())),
+            };
+
+            if chunk.done {
+                let usage = Usage::new(
+                    chunk.prompt_eval_count.unwrap_or_default(),
+                    chunk.eval_count.unwrap_or_default(),
+                );
+
+                return Some(Ok(StreamEvent::Done {
+                    model: chunk.model,
+                    usage: Some(usage),
+                }));
+            }
+
+            chunk
+                .message
+                .map(|message| message.content)
+                .filter(|content| !content.is_empty())
+                .map(|content| Ok(StreamEvent::Delta(content)))
+        });
+
+        Ok(Box::pin(stream))
+    }
+}
+
+#[async_trait]
+impl EmbedModel for Ollama {
+    fn batch_limits(&self) -> BatchLimits {
+        self.batch_limits
+    }
+
+    async fn embed_file(&self, chunked_contents: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        let res = self.embed_file_with_usage(chunked_contents).await?;
+
+        Ok(res.embeddings)
+    }
+
+    async fn embed_file_with_usage(
+        &self,
+        chunked_contents: Vec<String>,
+    ) -> anyhow::Result<EmbeddingResponse> {
+        let res = embed_in_batches(chunked_contents, &self.batch_limits, |batch| async {
+            Ok(self.embed(batch).await?)
+        })
+        .await?;
+
+        if res.embeddings.is_empty() {
+            return Err(anyhow::anyhow!(
+                "There were no embeddings returned by Ollama!"
+            ));
+        }
+
+        Ok(res)
+    }
+
+    async fn embed_sentence(&self, prompt: &str) -> anyhow::Result<Vec<f32>> {
+        let embedding = self
+            .embed(vec![prompt.to_owned()])
+            .await?
+            .embeddings
+            .into_iter()
+            .next();
+
+        match embedding {
+            Some(res) => Ok(res),
+            None => Err(anyhow::anyhow!(
+                "There were no embeddings returned by Ollama!"
+            )),
+        }
+    }
+}
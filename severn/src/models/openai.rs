@@ -0,0 +1,652 @@
+use crate::models::batching::embed_in_batches;
+use crate::models::streaming::single_response;
+use crate::models::usage::add_usage;
+use crate::models::{
+    BatchLimits, ChatRequest, Completion, ContentPart, EmbedModel, EmbeddingResponse, Message,
+    ModelSettings, PromptModel, Role, StreamEvent, TokenStream, ToolCall, Usage,
+};
+use anyhow::Result;
+use async_openai::{
+    error::OpenAIError,
+    types::{
+        ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessageArgs,
+        ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPartImage,
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestToolMessageArgs,
+        ChatCompletionRequestUserMessageArgs, ChatCompletionRequestUserMessageContent,
+        ChatCompletionRequestUserMessageContentPart, ChatCompletionStreamOptions,
+        ChatCompletionTool, ChatCompletionToolType, CreateChatCompletionRequest,
+        CreateChatCompletionRequestArgs, CreateEmbeddingRequest, EmbeddingInput, FunctionCall,
+        FunctionObject, ResponseFormat, ResponseFormatJsonSchema, Stop,
+    },
+    Client, Embeddings,
+};
+use async_trait::async_trait;
+use backoff::ExponentialBackoffBuilder;
+use futures::StreamExt;
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderValue, IntoHeaderName, AUTHORIZATION};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::Value;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use crate::errors::Error;
+use crate::tools::{call_tool, parse_arguments, MAX_TOOL_ITERATIONS};
+
+pub use async_openai::config::{AzureConfig, Config, OpenAIConfig};
+
+pub const DEFAULT_OPENAI_MODEL: &str = "gpt-4o";
+pub const DEFAULT_OPENAI_EMBEDDING_MODEL: &str = "text-embedding-ada-002";
+
+/// A `PromptModel` and `EmbedModel` for OpenAI, or anything that speaks the OpenAI API.
+///
+/// The config decides where requests go: `OpenAIConfig` for api.openai.com, `AzureConfig` for an
+/// Azure OpenAI deployment, or `OpenAICompatibleConfig` for servers like vLLM, llama.cpp,
+/// LM Studio or LiteLLM.
+#[derive(Clone)]
+pub struct OpenAI<C: Config = OpenAIConfig> {
+    client: Client<C>,
+    model: String,
+    embedding_model: String,
+    embedding_dimensions: Option<u32>,
+    batch_limits: BatchLimits,
+}
+
+impl OpenAI {
+    pub fn from_env() -> Result<Self> {
+        let api_key = std::env::var("OPENAI_API_KEY")?;
+        let config = OpenAIConfig::new()
+            .with_api_key(api_key)
+            .with_org_id("severn");
+
+        Ok(Self::from_config(config))
+    }
+
+    pub fn from_env_with_org_id(org_id: &str) -> Result<Self> {
+        let api_key = std::env::var("OPENAI_API_KEY")?;
+        let config = OpenAIConfig::new()
+            .with_api_key(api_key)
+            .with_org_id(org_id);
+
+        Ok(Self::from_config(config))
+    }
+
+    pub fn from_api_key(api_key: &str) -> Result<Self> {
+        let config = OpenAIConfig::new()
+            .with_api_key(api_key)
+            .with_org_id("severn");
+
+        Ok(Self::from_config(config))
+    }
+
+    pub fn from_api_key_with_org_id(api_key: &str, org_id: &str) -> Result<Self> {
+        let config = OpenAIConfig::new()
+            .with_api_key(api_key)
+            .with_org_id(org_id);
+
+        Ok(Self::from_config(config))
+    }
+}
+
+impl OpenAI<OpenAICompatibleConfig> {
+    /// Creates a client for an OpenAI-compatible server at `base_url`, for example
+    /// `http://localhost:8000/v1`.
+    pub fn compatible(base_url: &str) -> Self {
+        Self::from_config(OpenAICompatibleConfig::new(base_url))
+    }
+}
+
+impl OpenAI<AzureConfig> {
+    pub fn azure(api_base: &str, deployment_id: &str, api_version: &str, api_key: &str) -> Self {
+        let config = AzureConfig::new()
+            .with_api_base(api_base)
+            .with_deployment_id(deployment_id)
+            .with_api_version(api_version)
+            .with_api_key(api_key);
+
+        Self::from_config(config)
+    }
+}
+
+impl<C: Config> OpenAI<C> {
+    /// Rate limited requests aren't retried by the client, so they come back as
+    /// `Error::RateLimited` straight away. Wrap the model in a `RateLimited` to retry them.
+    pub fn from_config(config: C) -> Self {
+        //async-openai retries 429s itself by default, for up to 15 minutes
+        let backoff = ExponentialBackoffBuilder::new()
+            .with_max_elapsed_time(Some(Duration::ZERO))
+            .build();
+
+        Self {
+            client: Client::with_config(config).with_backoff(backoff),
+            model: DEFAULT_OPENAI_MODEL.to_string(),
+            embedding_model: DEFAULT_OPENAI_EMBEDDING_MODEL.to_string(),
+            embedding_dimensions: None,
+            batch_limits: BatchLimits::new(),
+        }
+    }
+
+    /// Sets the model name sent with each chat request. For Azure, this is usually ignored in
+    /// favour of the deployment.
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_owned();
+
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn with_embedding_model(mut self, embedding_model: &str) -> Self {
+        self.embedding_model = embedding_model.to_owned();
+
+        self
+    }
+
+    pub fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    /// Shortens the embeddings to `dimensions`. Only the `text-embedding-3` models and later
+    /// support this, so it's not sent unless it's set.
+    pub fn with_embedding_dimensions(mut self, dimensions: u32) -> Self {
+        self.embedding_dimensions = Some(dimensions);
+
+        self
+    }
+
+    pub fn embedding_dimensions(&self) -> Option<u32> {
+        self.embedding_dimensions
+    }
+
+    pub fn with_batch_limits(mut self, batch_limits: BatchLimits) -> Self {
+        self.batch_limits = batch_limits;
+
+        self
+    }
+
+    async fn embed(&self, input: EmbeddingInput) -> anyhow::Result<EmbeddingResponse> {
+        let embedding_request = CreateEmbeddingRequest {
+            model: self.embedding_model.to_owned(),
+            input,
+            encoding_format: None, // defaults to f32
+            user: None,
+            dimensions: self.embedding_dimensions,
+        };
+
+        let mut embeddings = Embeddings::new(&self.client)
+            .create(embedding_request)
+            .await
+            .map_err(from_openai_error)?;
+
+        if embeddings.data.is_empty() {
+            return Err(anyhow::anyhow!(
+                "There were no embeddings returned by OpenAI!"
+            ));
+        }
+
+        //Each embedding says which input it's for, so we don't rely on the order they came back in
+        embeddings.data.sort_by_key(|x| x.index);
+
+        Ok(EmbeddingResponse {
+            embeddings: embeddings.data.into_iter().map(|x| x.embedding).collect(),
+            model: embeddings.model,
+            usage: Some(Usage::new(embeddings.usage.prompt_tokens, 0)),
+        })
+    }
+}
+
+/// Config for servers that implement the OpenAI API somewhere other than api.openai.com.
+///
+/// Only the headers you set are sent: there's no `Authorization` header unless an API key is set,
+/// and none of OpenAI's own headers.
+#[derive(Clone, Debug)]
+pub struct OpenAICompatibleConfig {
+    api_base: String,
+    //Empty if no key has been set
+    api_key: Secret<String>,
+    path_prefix: String,
+    headers: HeaderMap,
+    query: Vec<(String, String)>,
+}
+
+impl OpenAICompatibleConfig {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            api_base: base_url.trim_end_matches('/').to_owned(),
+            api_key: Secret::new(String::new()),
+            path_prefix: String::new(),
+            headers: HeaderMap::new(),
+            query: Vec::new(),
+        }
+    }
+
+    /// Sent as a bearer token in the `Authorization` header.
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Secret::new(api_key.to_owned());
+
+        self
+    }
+
+    pub fn add_header<K: IntoHeaderName>(mut self, key: K, val: String) -> Result<Self, Error> {
+        self.headers.insert(key, HeaderValue::try_from(val)?);
+
+        Ok(self)
+    }
+
+    pub fn set_headers(mut self, header_map: HeaderMap) -> Self {
+        self.headers = header_map;
+
+        self
+    }
+
+    /// Adds a query parameter to every request, for example `api-version`.
+    pub fn add_query_param(mut self, key: &str, val: &str) -> Self {
+        self.query.push((key.to_owned(), val.to_owned()));
+
+        self
+    }
+
+    /// Inserts a path between the base URL and the endpoint, for example
+    /// `/openai/deployments/my-deployment`.
+    pub fn with_path_prefix(mut self, path_prefix: &str) -> Self {
+        self.path_prefix = path_prefix.trim_end_matches('/').to_owned();
+
+        self
+    }
+}
+
+impl Config for OpenAICompatibleConfig {
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let api_key = self.api_key.expose_secret();
+
+        if !api_key.is_empty() {
+            //The key is only invalid as a header if it has characters like newlines in it, in
+            //which case it's left off and the server will say it's missing
+            if let Ok(value) = HeaderValue::from_str(&format!("Bearer {api_key}")) {
+                headers.insert(AUTHORIZATION, value);
+            }
+        }
+
+        headers.extend(self.headers.clone());
+
+        headers
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}{}", self.api_base(), self.path_prefix, path)
+    }
+
+    fn query(&self) -> Vec<(&str, &str)> {
+        self.query
+            .iter()
+            .map(|(key, val)| (key.as_str(), val.as_str()))
+            .collect()
+    }
+
+    fn api_base(&self) -> &str {
+        &self.api_base
+    }
+
+    fn api_key(&self) -> &Secret<String> {
+        &self.api_key
+    }
+}
+
+#[async_trait]
+impl<C: Config + Send + Sync> PromptModel for OpenAI<C> {
+    fn supports_images(&self) -> bool {
+        true
+    }
+
+    /// Images are sent as image content parts.
+    async fn chat(&self, request: &ChatRequest) -> Result<Completion, Error> {
+        let settings = request.settings();
+        let model = settings.model.as_deref().unwrap_or(&self.model);
+        let mut messages = request.messages().to_vec();
+
+        let tools = request.tools();
+        let tool_definitions = tools
+            .iter()
+            .map(|tool| ChatCompletionTool {
+                r#type: ChatCompletionToolType::Function,
+                function: FunctionObject {
+                    name: tool.name(),
+                    description: Some(tool.description()),
+                    parameters: Some(tool.parameters()),
+                    strict: None,
+                },
+            })
+            .collect::<Vec<ChatCompletionTool>>();
+
+        let output_schema = request.output_schema();
+        let response_format = output_schema.map(|schema| ResponseFormat::JsonSchema {
+            json_schema: ResponseFormatJsonSchema {
+                description: None,
+                name: schema.name().to_owned(),
+                schema: Some(schema.schema().clone()),
+                strict: None,
+            },
+        });
+
+        //Every round of the tool loop is billed, so we add up the usage from each of them
+        let mut usage = None;
+
+        for _ in 0..=MAX_TOOL_ITERATIONS {
+            let mut chat_request = CreateChatCompletionRequestArgs::default()
+                .model(model)
+                .messages(to_openai_messages(&messages)?)
+                .build()?;
+
+            apply_settings(&mut chat_request, settings);
+
+            if !tool_definitions.is_empty() {
+                chat_request.tools = Some(tool_definitions.clone());
+            }
+
+            chat_request.response_format = response_format.clone();
+
+            let res = self
+                .client
+                .chat()
+                .create(chat_request)
+                .await
+                .map_err(from_openai_error)?;
+
+            usage = add_usage(
+                usage,
+                res.usage
+                    .map(|usage| Usage::new(usage.prompt_tokens, usage.completion_tokens)),
+            );
+
+            //We extract the first one
+            let Some(choice) = res.choices.into_iter().next() else {
+                return Err(Error::OptionIsNone);
+            };
+
+            let tool_calls = choice.message.tool_calls.unwrap_or_default();
+
+            if tool_calls.is_empty() {
+                let res = choice.message.content.ok_or(Error::OptionIsNone)?;
+
+                if let Some(schema) = output_schema {
+                    schema.validate(&res)?;
+                }
+
+                return Ok(Completion::new(res, model).with_usage(usage));
+            }
+
+            //The model wants to call tools, so we run them and feed the results back
+            let mut results = Vec::new();
+            let mut calls = Vec::new();
+
+            for tool_call in tool_calls {
+                let (arguments, content) = match parse_arguments(&tool_call.function.arguments) {
+                    Ok(arguments) => {
+                        let content =
+                            call_tool(tools, &tool_call.function.name, arguments.clone()).await;
+
+                        (arguments, content)
+                    }
+                    //The raw arguments are kept so the model sees what it actually sent
+                    Err(e) => (Value::String(tool_call.function.arguments), e),
+                };
+
+                results.push(Message::tool_result(&tool_call.id, &content));
+                calls.push(ToolCall {
+                    id: tool_call.id,
+                    name: tool_call.function.name,
+                    arguments,
+                });
+            }
+
+            messages.push(Message::new(Role::Assistant).with_tool_calls(calls));
+            messages.extend(results);
+        }
+
+        Err(Error::ToolLoopLimit(
+            request.agent().to_owned(),
+            MAX_TOOL_ITERATIONS,
+        ))
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<TokenStream, Error> {
+        //Tool calls and structured output need the whole response, so they don't get streamed
+        if !request.tools().is_empty() || request.output_schema().is_some() {
+            let res = self.chat(request).await?;
+
+            return Ok(single_response(res));
+        }
+
+        let settings = request.settings();
+        let model = settings.model.clone().unwrap_or(self.model.to_owned());
+
+        let mut chat_request = CreateChatCompletionRequestArgs::default()
+            .model(&model)
+            .messages(to_openai_messages(request.messages())?)
+            .build()?;
+
+        apply_settings(&mut chat_request, settings);
+        chat_request.stream_options = Some(ChatCompletionStreamOptions {
+            include_usage: true,
+        });
+
+        let mut chunks = self
+            .client
+            .chat()
+            .create_stream(chat_request)
+            .await
+            .map_err(from_openai_error)?;
+
+        //A rejected request only shows up as the first item of the stream, so we check it here
+        //where it can still be returned (and retried) as an error
+        let first = match chunks.next().await {
+            Some(Err(e)) => return Err(from_openai_error(e)),
+            first => first,
+        };
+        let chunks = futures::stream::iter(first).chain(chunks).boxed();
+
+        let stream = futures::stream::unfold(Some((chunks, model)), |state| async move {
+            let (mut chunks, model) = state?;
+
+            loop {
+                match chunks.next().await {
+                    Some(Ok(chunk)) => {
+                        //With include_usage set, the last chunk carries the usage
+                        if let Some(usage) = chunk.usage {
+                            let usage = Usage::new(usage.prompt_tokens, usage.completion_tokens);
+
+                            let done = StreamEvent::Done {
+                                model,
+                                usage: Some(usage),
+                            };
+
+                            return Some((Ok(done), None));
+                        }
+
+                        let delta = chunk
+                            .choices
+                            .into_iter()
+                            .next()
+                            .and_then(|choice| choice.delta.content)
+                            .filter(|delta| !delta.is_empty());
+
+                        if let Some(delta) = delta {
+                            return Some((Ok(StreamEvent::Delta(delta)), Some((chunks, model))));
+                        }
+                    }
+                    Some(Err(e)) => return Some((Err(from_openai_error(e)), None)),
+                    //Some compatible servers ignore include_usage, so there's no usage chunk
+                    None => return Some((Ok(StreamEvent::Done { model, usage: None }), None)),
+                }
+            }
+        });
+
+        Ok(Box::pin(stream))
+    }
+}
+
+fn to_openai_messages(messages: &[Message]) -> Result<Vec<ChatCompletionRequestMessage>, Error> {
+    messages.iter().map(to_openai_message).collect()
+}
+
+fn to_openai_message(message: &Message) -> Result<ChatCompletionRequestMessage, Error> {
+    let res = match message.role() {
+        Role::System => ChatCompletionRequestMessage::System(
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(message.text())
+                .build()?,
+        ),
+        Role::User => {
+            //Plain text is sent as a string, since not every compatible server accepts parts
+            let content = if message.images().is_empty() {
+                ChatCompletionRequestUserMessageContent::Text(message.text())
+            } else {
+                ChatCompletionRequestUserMessageContent::Array(
+                    message
+                        .content()
+                        .iter()
+                        .map(|part| match part {
+                            ContentPart::Text(text) => {
+                                ChatCompletionRequestUserMessageContentPart::Text(
+                                    text.as_str().into(),
+                                )
+                            }
+                            ContentPart::Image(image) => {
+                                ChatCompletionRequestUserMessageContentPart::ImageUrl(
+                                    ChatCompletionRequestMessageContentPartImage {
+                                        image_url: image.data_url().into(),
+                                    },
+                                )
+                            }
+                        })
+                        .collect(),
+                )
+            };
+
+            ChatCompletionRequestMessage::User(
+                ChatCompletionRequestUserMessageArgs::default()
+                    .content(content)
+                    .build()?,
+            )
+        }
+        Role::Assistant => {
+            let mut args = ChatCompletionRequestAssistantMessageArgs::default();
+
+            if !message.content().is_empty() {
+                args.content(message.text());
+            }
+
+            if !message.tool_calls().is_empty() {
+                args.tool_calls(
+                    message
+                        .tool_calls()
+                        .iter()
+                        .map(|tool_call| ChatCompletionMessageToolCall {
+                            id: tool_call.id.to_owned(),
+                            r#type: ChatCompletionToolType::Function,
+                            function: FunctionCall {
+                                name: tool_call.name.to_owned(),
+                                arguments: match &tool_call.arguments {
+                                    Value::String(arguments) => arguments.to_owned(),
+                                    arguments => arguments.to_string(),
+                                },
+                            },
+                        })
+                        .collect::<Vec<ChatCompletionMessageToolCall>>(),
+                );
+            }
+
+            ChatCompletionRequestMessage::Assistant(args.build()?)
+        }
+        Role::Tool => ChatCompletionRequestMessage::Tool(
+            ChatCompletionRequestToolMessageArgs::default()
+                .content(message.text())
+                .tool_call_id(message.tool_call_id().unwrap_or_default())
+                .build()?,
+        ),
+    };
+
+    Ok(res)
+}
+
+/// async-openai hands back a 429 as a plain API error, so rate limits are picked out by their error
+/// code. OpenAI only says how long to wait in the message, as in "Please try again in 1.5s."
+fn from_openai_error(e: OpenAIError) -> Error {
+    match &e {
+        OpenAIError::ApiError(err) if err.code.as_deref() == Some("rate_limit_exceeded") => {
+            Error::RateLimited {
+                retry_after: retry_after(&err.message),
+            }
+        }
+        OpenAIError::StreamError(message) if message.starts_with("Invalid status code: 429") => {
+            Error::RateLimited { retry_after: None }
+        }
+        _ => Error::LLMError(e),
+    }
+}
+
+static RETRY_AFTER_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"try again in (\d+(?:\.\d+)?)(ms|s)\b").unwrap());
+
+fn retry_after(message: &str) -> Option<Duration> {
+    let caps = RETRY_AFTER_PATTERN.captures(message)?;
+    let amount = caps[1].parse::<f64>().ok()?;
+
+    match &caps[2] {
+        "ms" => Some(Duration::from_secs_f64(amount / 1000.0)),
+        _ => Some(Duration::from_secs_f64(amount)),
+    }
+}
+
+fn apply_settings(request: &mut CreateChatCompletionRequest, settings: &ModelSettings) {
+    request.temperature = settings.temperature;
+    request.top_p = settings.top_p;
+    request.max_tokens = settings.max_tokens;
+    request.stop = settings.stop.clone().map(Stop::StringArray);
+    request.seed = settings.seed;
+    request.presence_penalty = settings.presence_penalty;
+    request.frequency_penalty = settings.frequency_penalty;
+}
+
+#[async_trait]
+impl<C: Config + Send + Sync> EmbedModel for OpenAI<C> {
+    fn batch_limits(&self) -> BatchLimits {
+        self.batch_limits
+    }
+
+    async fn embed_file(&self, chunked_contents: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        let res = self.embed_file_with_usage(chunked_contents).await?;
+
+        Ok(res.embeddings)
+    }
+
+    /// Embeds the chunks, splitting them into as many requests as the batch limits need.
+    async fn embed_file_with_usage(
+        &self,
+        chunked_contents: Vec<String>,
+    ) -> anyhow::Result<EmbeddingResponse> {
+        embed_in_batches(chunked_contents, &self.batch_limits, |batch| {
+            self.embed(EmbeddingInput::StringArray(batch))
+        })
+        .await
+    }
+
+    async fn embed_sentence(&self, prompt: &str) -> anyhow::Result<Vec<f32>> {
+        let embedding = self
+            .embed(EmbeddingInput::String(prompt.to_owned()))
+            .await?
+            .embeddings
+            .into_iter()
+            .next();
+
+        match embedding {
+            Some(res) => Ok(res),
+            None => Err(anyhow::anyhow!(
+                "There were no embeddings returned by OpenAI!"
+            )),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::pin::Pin;
+
+use futures::{Stream, StreamExt};
+use reqwest::Response;
+
+use crate::errors::Error;
+use crate::models::{Completion, Usage};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamEvent {
+    /// The next piece of text from the model.
+    Delta(String),
+    /// The model has finished. Usage is included if the provider reported it.
+    Done { model: String, usage: Option<Usage> },
+}
+
+pub type TokenStream = Pin<Box<dyn Stream<Item = Result<StreamEvent, Error>> + Send>>;
+
+/// A stream that returns a whole answer at once, for when a model can't stream.
+pub(crate) fn single_response(res: Completion) -> TokenStream {
+    Box::pin(futures::stream::iter([
+        Ok(StreamEvent::Delta(res.content)),
+        Ok(StreamEvent::Done {
+            model: res.model,
+            usage: res.usage,
+        }),
+    ]))
+}
+
+/// Splits a streamed HTTP response body into lines, for providers that stream newline-delimited
+/// JSON or server-sent events.
+pub(crate) fn lines(res: Response) -> impl Stream<Item = Result<String, Error>> + Send {
+    let state = (res.bytes_stream().boxed(), Vec::<u8>::new(), false);
+
+    futures::stream::unfold(state, |(mut body, mut buffer, mut finished)| async move {
+        loop {
+            if let Some(idx) = buffer.iter().position(|b| *b == b'\n') {
+                let line = buffer.drain(..=idx).collect::<Vec<u8>>();
+                let line = String::from_utf8_lossy(&line).trim_end().to_owned();
+
+                return Some((Ok(line), (body, buffer, finished)));
+            }
+
+            if finished {
+                if buffer.is_empty() {
+                    return None;
+                }
+
+                let line = String::from_utf8_lossy(&buffer).trim_end().to_owned();
+                buffer.clear();
+
+                return Some((Ok(line), (body, buffer, finished)));
+            }
+
+            match body.next().await {
+                Some(Ok(bytes)) => buffer.extend_from_slice(&bytes),
+                Some(Err(e)) => {
+                    finished = true;
+                    buffer.clear();
+
+                    return Some((Err(e.into()), (body, buffer, finished)));
+                }
+                None => finished = true,
+            }
+        }
+    })
+}
@@ -0,0 +1,5 @@
+/// A rough token estimate (about four characters per token), for sizing a request before it's
+/// sent.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::ops::{Add, AddAssign};
+
+/// Token counts for a single model call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+impl Usage {
+    pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+}
+
+impl Add for Usage {
+    type Output = Usage;
+
+    fn add(self, other: Usage) -> Usage {
+        Usage {
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            completion_tokens: self.completion_tokens + other.completion_tokens,
+            total_tokens: self.total_tokens + other.total_tokens,
+        }
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        *self = *self + other;
+    }
+}
+
+/// Adds up usage from several calls, for example each round of a tool loop. The total is `None`
+/// only if none of the calls reported usage.
+pub(crate) fn add_usage(total: Option<Usage>, usage: Option<Usage>) -> Option<Usage> {
+    match (total, usage) {
+        (Some(total), Some(usage)) => Some(total + usage),
+        (total, usage) => total.or(usage),
+    }
+}
+
+/// The answer from a single prompt, along with the model that gave it and the tokens it used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Completion {
+    pub content: String,
+    pub model: String,
+    /// `None` if the provider didn't report usage.
+    pub usage: Option<Usage>,
+}
+
+impl Completion {
+    pub fn new(content: String, model: &str) -> Self {
+        Self {
+            content,
+            model: model.to_owned(),
+            usage: None,
+        }
+    }
+
+    pub fn with_usage(mut self, usage: Option<Usage>) -> Self {
+        self.usage = usage;
+
+        self
+    }
+}
+
+/// Embeddings for a batch of text, along with the model that made them and the tokens it used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmbeddingResponse {
+    pub embeddings: Vec<Vec<f32>>,
+    /// Empty if the embedder doesn't say which model it used.
+    pub model: String,
+    /// `None` if the provider didn't report usage.
+    pub usage: Option<Usage>,
+}
+
+/// What a model charges, in dollars per million tokens.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl ModelPrice {
+    pub fn per_million_tokens(prompt: f64, completion: f64) -> Self {
+        Self { prompt, completion }
+    }
+
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.prompt_tokens as f64 * self.prompt
+            + usage.completion_tokens as f64 * self.completion)
+            / 1_000_000.0
+    }
+}
+
+/// Prices for the models you use, for estimating what a run cost.
+///
+/// Severn doesn't ship any prices since providers change them, so add the ones you need. A model
+/// name matches the longest price name it starts with, so a price for `gpt-4o` also covers dated
+/// versions like `gpt-4o-2024-08-06`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PriceTable {
+    prices: HashMap<String, ModelPrice>,
+}
+
+impl PriceTable {
+    pub fn new() -> Self {
+        Self {
+            prices: HashMap::new(),
+        }
+    }
+
+    pub fn with_price(mut self, model: &str, price: ModelPrice) -> Self {
+        self.prices.insert(model.to_owned(), price);
+
+        self
+    }
+
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        if let Some(price) = self.prices.get(model) {
+            return Some(*price);
+        }
+
+        self.prices
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, price)| *price)
+    }
+
+    /// The estimated cost of a call, or `None` if there's no price for the model.
+    pub fn cost(&self, model: &str, usage: &Usage) -> Option<f64> {
+        self.price(model).map(|price| price.cost(usage))
+    }
+}
+
+/// Usage and estimated cost added up over a number of calls.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UsageTotals {
+    pub calls: usize,
+    pub usage: Usage,
+    /// Estimated cost in dollars of the calls that could be priced.
+    pub cost: f64,
+    /// Calls that didn't report usage or used a model missing from the price table, so aren't
+    /// included in the cost.
+    pub unpriced_calls: usize,
+}
+
+impl UsageTotals {
+    fn record(&mut self, usage: Option<Usage>, cost: Option<f64>) {
+        self.calls += 1;
+
+        if let Some(usage) = usage {
+            self.usage += usage;
+        }
+
+        match cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced_calls += 1,
+        }
+    }
+
+    fn merge(&mut self, other: &UsageTotals) {
+        self.calls += other.calls;
+        self.usage += other.usage;
+        self.cost += other.cost;
+        self.unpriced_calls += other.unpriced_calls;
+    }
+}
+
+/// Usage and estimated cost for a run, per agent and in total.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UsageReport {
+    agents: HashMap<String, UsageTotals>,
+    total: UsageTotals,
+}
+
+impl UsageReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a call made by `agent`, pricing it with `prices`.
+    pub fn record(&mut self, agent: &str, model: &str, usage: Option<Usage>, prices: &PriceTable) {
+        let cost = usage.and_then(|usage| prices.cost(model, &usage));
+
+        self.agents
+            .entry(agent.to_owned())
+            .or_default()
+            .record(usage, cost);
+        self.total.record(usage, cost);
+    }
+
+    pub fn record_completion(&mut self, agent: &str, completion: &Completion, prices: &PriceTable) {
+        self.record(agent, &completion.model, completion.usage, prices);
+    }
+
+    /// Adds another report into this one, for example to total up several runs of a workflow.
+    pub fn merge(&mut self, other: &UsageReport) {
+        for (agent, totals) in &other.agents {
+            self.agents
+                .entry(agent.to_owned())
+                .or_default()
+                .merge(totals);
+        }
+
+        self.total.merge(&other.total);
+    }
+
+    pub fn agent(&self, name: &str) -> Option<&UsageTotals> {
+        self.agents.get(name)
+    }
+
+    pub fn agents(&self) -> &HashMap<String, UsageTotals> {
+        &self.agents
+    }
+
+    pub fn total(&self) -> &UsageTotals {
+        &self.total
+    }
+}
@@ -1,10 +1,55 @@
+use crate::agents::premade::Planner;
+use crate::blackboard::{Blackboard, INITIAL_DATA_KEY};
+use crate::consensus::{Consensus, ConsensusRun};
 use crate::errors::Error;
-use crate::models::PromptModel;
+use crate::guardrails::{check_input, run_guardrails, Checked, Guardrail, MAX_GUARDRAIL_REASKS};
+use crate::memory::Memory;
+use crate::models::streaming::single_response;
+use crate::models::usage::add_usage;
+use crate::models::{
+    ChatRequest, Completion, Image, Message, ModelSettings, PriceTable, PromptModel, StreamEvent,
+    Usage, UsageReport,
+};
+use crate::planning::{
+    plan_request, step_context, Plan, PlanRun, PlanStep, StepResult, DEFAULT_MAX_REPLANS,
+};
+use crate::structured::{parse_output, OutputSchema};
 use crate::{agents::traits::Agent, data_sources::DataSource};
+use futures::channel::mpsc::{self, UnboundedSender};
+use futures::stream::{self, BoxStream};
+use futures::{future, StreamExt};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
 use std::sync::Arc;
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum PipelineEvent {
+    /// An agent has started. Only sent when step events are enabled.
+    StepStarted { agent: String },
+    /// An agent has finished, with its full output. Only sent when step events are enabled.
+    StepFinished { agent: String, output: String },
+    /// The next piece of text from the last agent in the pipeline.
+    Delta(String),
+    /// The pipeline has finished. `usage` is for the last agent's call, if the model reported it,
+    /// and `report` covers the whole run.
+    Done {
+        output: String,
+        usage: Option<Usage>,
+        report: UsageReport,
+    },
+}
+
 pub struct Pipeline {
     agents: Vec<Arc<dyn Agent>>,
+    model: Option<Arc<dyn PromptModel>>,
+    agent_models: HashMap<String, Arc<dyn PromptModel>>,
+    settings: ModelSettings,
+    prices: PriceTable,
+    planner: Arc<dyn Agent>,
+    max_replans: usize,
+    memory: Option<Memory>,
+    input_guardrails: Vec<Arc<dyn Guardrail>>,
+    output_guardrails: Vec<Arc<dyn Guardrail>>,
 }
 
 impl Default for Pipeline {
@@ -15,7 +60,101 @@ impl Default for Pipeline {
 
 impl Pipeline {
     pub fn new() -> Self {
-        Self { agents: Vec::new() }
+        Self {
+            agents: Vec::new(),
+            model: None,
+            agent_models: HashMap::new(),
+            settings: ModelSettings::default(),
+            prices: PriceTable::default(),
+            planner: Arc::new(Planner::new().with_output_schema(OutputSchema::of::<Plan>())),
+            max_replans: DEFAULT_MAX_REPLANS,
+            memory: None,
+            input_guardrails: Vec::new(),
+            output_guardrails: Vec::new(),
+        }
+    }
+
+    /// Sets the model used by every agent that doesn't have its own override.
+    pub fn with_model(mut self, model: Arc<dyn PromptModel>) -> Self {
+        self.model = Some(model);
+
+        self
+    }
+
+    pub fn model(&self) -> Option<&Arc<dyn PromptModel>> {
+        self.model.as_ref()
+    }
+
+    /// Sets the default model settings. An agent's own settings take priority over these.
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+
+    pub fn settings(&self) -> &ModelSettings {
+        &self.settings
+    }
+
+    /// Sets the prices used to estimate the cost of each run.
+    pub fn with_prices(mut self, prices: PriceTable) -> Self {
+        self.prices = prices;
+
+        self
+    }
+
+    pub fn prices(&self) -> &PriceTable {
+        &self.prices
+    }
+
+    /// Sets the agent that writes the plan for `run_pipeline_with_plan`. Defaults to
+    /// the premade `Planner`. The planner isn't one of the pipeline's agents, but a model can still be set
+    /// for it by name with `with_agent_model`.
+    pub fn with_planner(mut self, planner: Arc<dyn Agent>) -> Self {
+        self.planner = planner;
+
+        self
+    }
+
+    pub fn planner(&self) -> &Arc<dyn Agent> {
+        &self.planner
+    }
+
+    /// Sets how many times `run_pipeline_with_plan` asks for a new plan after a step fails or a
+    /// plan can't be used.
+    pub fn with_max_replans(mut self, max_replans: usize) -> Self {
+        self.max_replans = max_replans;
+
+        self
+    }
+
+    pub fn max_replans(&self) -> usize {
+        self.max_replans
+    }
+
+    /// Sets the long-term memory used by `run_pipeline_with_memory`.
+    pub fn with_memory(mut self, memory: Memory) -> Self {
+        self.memory = Some(memory);
+
+        self
+    }
+
+    pub fn memory(&self) -> Option<&Memory> {
+        self.memory.as_ref()
+    }
+
+    /// Adds a guardrail that every agent's prompt and context go through before they're sent.
+    pub fn with_input_guardrail(mut self, guardrail: Arc<dyn Guardrail>) -> Self {
+        self.input_guardrails.push(guardrail);
+
+        self
+    }
+
+    /// Adds a guardrail that every agent's answer goes through.
+    pub fn with_output_guardrail(mut self, guardrail: Arc<dyn Guardrail>) -> Self {
+        self.output_guardrails.push(guardrail);
+
+        self
     }
 
     pub fn add_agent(mut self, agent: Arc<dyn Agent>) -> Self {
@@ -24,12 +163,287 @@ impl Pipeline {
         self
     }
 
-    pub async fn run_pipeline<P: PromptModel>(
+    /// Adds an agent that uses `model` instead of the pipeline's default model.
+    pub fn add_agent_with_model(
+        mut self,
+        agent: Arc<dyn Agent>,
+        model: Arc<dyn PromptModel>,
+    ) -> Self {
+        self.agent_models.insert(agent.name(), model);
+        self.agents.push(agent);
+
+        self
+    }
+
+    /// Overrides the model for an agent that has already been added, by name.
+    pub fn with_agent_model(mut self, name: &str, model: Arc<dyn PromptModel>) -> Self {
+        self.agent_models.insert(name.to_owned(), model);
+
+        self
+    }
+
+    fn model_for(&self, agent: &Arc<dyn Agent>) -> Result<&Arc<dyn PromptModel>, Error> {
+        self.agent_models
+            .get(&agent.name())
+            .or(self.model.as_ref())
+            .ok_or_else(|| Error::NoModelSet(agent.name()))
+    }
+
+    /// The request sent for an agent, using the pipeline's settings for anything the agent
+    /// leaves unset.
+    fn request_for(
+        &self,
+        agent: &Arc<dyn Agent>,
+        prompt: &str,
+        images: &[Image],
+        context: &str,
+        blackboard: &Blackboard,
+    ) -> Result<ChatRequest, Error> {
+        let request = ChatRequest::for_agent(agent, prompt, context)
+            .with_settings(agent.settings().merge(&self.settings))
+            .with_images(images);
+
+        match agent.system_template() {
+            Some(template) => {
+                let mut values = agent.variables();
+
+                for name in template.variables() {
+                    if let Some(value) = blackboard.get_text(&name) {
+                        values.insert(name, value);
+                    }
+                }
+
+                //Unfilled placeholders are caught here rather than being sent to the model
+                let missing = template.missing_variables(&values);
+
+                if !missing.is_empty() {
+                    return Err(Error::UnresolvedPlaceholders {
+                        agent: agent.name(),
+                        placeholders: missing,
+                    });
+                }
+
+                Ok(request.with_system_message(&template.fill(&values)))
+            }
+            None => Ok(request),
+        }
+    }
+
+    /// Runs the input guardrails and builds the agent's request, checking that its model can
+    /// take the request before anything is sent.
+    async fn prepare_request(
+        &self,
+        prompt: &str,
+        images: &[Image],
+        context: &str,
+        blackboard: &Blackboard,
+        agent: &Arc<dyn Agent>,
+    ) -> Result<(ChatRequest, &Arc<dyn PromptModel>), Error> {
+        let name = agent.name();
+        let input_guardrails = self.input_guardrails_for(agent);
+
+        let prompt = check_input(&input_guardrails, &name, prompt).await?;
+        let context = check_input(&input_guardrails, &name, context).await?;
+
+        let request = self.request_for(agent, &prompt, images, &context, blackboard)?;
+        let model = self.model_for(agent)?;
+
+        if request.has_images() && !model.supports_images() {
+            return Err(Error::ImagesNotSupported(name));
+        }
+
+        Ok((request, model))
+    }
+
+    pub(crate) async fn prompt_agent(
+        &self,
+        prompt: &str,
+        images: &[Image],
+        context: String,
+        blackboard: &Blackboard,
+        agent: &Arc<dyn Agent>,
+    ) -> Result<Completion, Error> {
+        let name = agent.name();
+
+        let (mut request, model) = self
+            .prepare_request(prompt, images, &context, blackboard, agent)
+            .await?;
+        let mut res = model.chat(&request).await?;
+
+        let output_guardrails = self.output_guardrails_for(agent);
+        let mut reasks = 0;
+
+        loop {
+            match run_guardrails(&output_guardrails, &name, &res.content).await? {
+                Checked::Passed(content) => {
+                    res.content = content;
+
+                    return Ok(res);
+                }
+                Checked::Reask {
+                    guardrail,
+                    feedback,
+                } => {
+                    if reasks == MAX_GUARDRAIL_REASKS {
+                        return Err(Error::GuardrailViolation {
+                            guardrail,
+                            agent: name,
+                            reason: feedback,
+                        });
+                    }
+
+                    reasks += 1;
+
+                    //The agent sees its rejected answer and why, then tries again
+                    request = request
+                        .with_message(Message::assistant(&res.content))
+                        .with_message(Message::user(&format!(
+                            "That answer was rejected. {feedback} Please answer again."
+                        )));
+
+                    let retry = model.chat(&request).await?;
+
+                    res = Completion {
+                        usage: add_usage(res.usage, retry.usage),
+                        ..retry
+                    };
+                }
+            }
+        }
+    }
+
+    fn input_guardrails_for(&self, agent: &Arc<dyn Agent>) -> Vec<Arc<dyn Guardrail>> {
+        [self.input_guardrails.clone(), agent.input_guardrails()].concat()
+    }
+
+    fn output_guardrails_for(&self, agent: &Arc<dyn Agent>) -> Vec<Arc<dyn Guardrail>> {
+        [self.output_guardrails.clone(), agent.output_guardrails()].concat()
+    }
+
+    pub async fn run_pipeline(&self, prompt: String) -> Result<String, Error> {
+        let (res, _, _) = self.run_steps(&prompt, &[], "", Blackboard::new()).await?;
+
+        Ok(res)
+    }
+
+    /// Runs the pipeline with `variables` written to the blackboard, where they fill the
+    /// placeholders in agents' system templates.
+    pub async fn run_pipeline_with_variables(
+        &self,
+        prompt: String,
+        variables: HashMap<String, String>,
+    ) -> Result<String, Error> {
+        let mut blackboard = Blackboard::new();
+
+        for (key, value) in variables {
+            blackboard.insert(&key, value)?;
+        }
+
+        let (res, _, _) = self.run_steps(&prompt, &[], "", blackboard).await?;
+
+        Ok(res)
+    }
+
+    /// Runs the pipeline with the memories in `scope` that are closest to the prompt recalled
+    /// into every agent's context, then remembers the prompt and answer in `scope` for later runs.
+    pub async fn run_pipeline_with_memory(
+        &self,
+        prompt: String,
+        scope: &str,
+    ) -> Result<String, Error> {
+        let Some(memory) = &self.memory else {
+            return Err(Error::NoMemorySet);
+        };
+
+        let recalled = memory.recall_context(scope, &prompt).await?;
+
+        let (res, _, _) = self
+            .run_steps(&prompt, &[], &recalled, Blackboard::new())
+            .await?;
+
+        memory
+            .remember(scope, &format!("Prompt: {prompt}\nAnswer: {res}"))
+            .await?;
+
+        Ok(res)
+    }
+
+    /// Runs the pipeline with images attached to the prompt. Every agent is shown the images, so
+    /// every agent's model needs to support them.
+    pub async fn run_pipeline_with_images(
         &self,
         prompt: String,
-        model: P,
+        images: Vec<Image>,
     ) -> Result<String, Error> {
-        let mut context = String::from("None");
+        let (res, _, _) = self
+            .run_steps(&prompt, &images, "", Blackboard::new())
+            .await?;
+
+        Ok(res)
+    }
+
+    /// Runs the pipeline, returning the last agent's output along with the tokens used and
+    /// estimated cost of every agent in the run.
+    pub async fn run_pipeline_with_usage(
+        &self,
+        prompt: String,
+    ) -> Result<(String, UsageReport), Error> {
+        let (res, _, report) = self.run_steps(&prompt, &[], "", Blackboard::new()).await?;
+
+        Ok((res, report))
+    }
+
+    /// Runs the pipeline with the data source's text as the first agent's context. Any images the
+    /// data source has are attached to the prompt for every agent.
+    pub async fn run_pipeline_with_initial_data<D: DataSource>(
+        &self,
+        prompt: String,
+        data_source: D,
+    ) -> Result<String, Error> {
+        let mut blackboard = Blackboard::new();
+        blackboard.insert(INITIAL_DATA_KEY, data_source.retrieve_data().await?)?;
+
+        let images = data_source.retrieve_images().await?;
+
+        let (res, _, _) = self.run_steps(&prompt, &images, "", blackboard).await?;
+
+        Ok(res)
+    }
+
+    /// Runs the pipeline and deserializes the last agent's output into `T`. The last agent should
+    /// usually declare an `output_schema` for `T`.
+    pub async fn run_pipeline_structured<T: DeserializeOwned>(
+        &self,
+        prompt: String,
+    ) -> Result<T, Error> {
+        let res = self.run_pipeline(prompt).await?;
+
+        parse_output(&res)
+    }
+
+    /// Runs the pipeline against a pre-populated blackboard, returning the blackboard with the
+    /// output of every agent written into it.
+    pub async fn run_pipeline_with_blackboard(
+        &self,
+        prompt: String,
+        blackboard: Blackboard,
+    ) -> Result<Blackboard, Error> {
+        let (_, blackboard, _) = self.run_steps(&prompt, &[], "", blackboard).await?;
+
+        Ok(blackboard)
+    }
+
+    async fn run_steps(
+        &self,
+        prompt: &str,
+        images: &[Image],
+        recalled: &str,
+        mut blackboard: Blackboard,
+    ) -> Result<(String, Blackboard, UsageReport), Error> {
+        let mut report = UsageReport::new();
+        let mut previous = blackboard
+            .get_text(INITIAL_DATA_KEY)
+            .unwrap_or_else(|| String::from("None"));
 
         let mut agents = self.agents.iter().peekable();
 
@@ -38,82 +452,338 @@ impl Pipeline {
         }
 
         while let Some(agent) = agents.next() {
-            let res = model.prompt(&prompt, context.to_owned(), agent).await?;
+            let mut context = context_for(agent, &previous, &blackboard)?;
 
-            context = res.clone();
+            if !recalled.is_empty() {
+                context = format!("{recalled}\n\n{context}");
+            }
+
+            let res = self
+                .prompt_agent(prompt, images, context, &blackboard, agent)
+                .await?;
+
+            report.record_completion(&agent.name(), &res, &self.prices);
+            blackboard.insert(&agent.writes(), &res.content)?;
 
             if agents.peek().is_none() {
-                return Ok(res);
+                return Ok((res.content, blackboard, report));
             }
+
+            let res = res.content;
+
+            previous = res;
         }
 
         Err(Error::NoAgentsExist)
     }
 
-    pub async fn run_pipeline_with_initial_data<P: PromptModel, D: DataSource>(
+    /// Runs `consensus` with its agents prompted like the pipeline's own: each uses its model
+    /// override or the pipeline's model, the pipeline's settings and guardrails, and its system
+    /// template.
+    pub async fn run_consensus(
         &self,
+        consensus: &Consensus,
         prompt: String,
-        model: P,
-        data_source: D,
+    ) -> Result<ConsensusRun, Error> {
+        consensus.run_in(self, &prompt, String::from("None")).await
+    }
+
+    /// Asks the planner to break `goal` into steps, each handled by one of the pipeline's agents,
+    /// then runs them in order. Each step is given the goal and the results of the steps before
+    /// it. If the planner's reply isn't a usable plan, or a step can't be carried out because it
+    /// names an unknown agent or has no task, the planner is asked for a new plan for the rest of
+    /// the goal, up to `max_replans` times, after which the error is returned. Any other error,
+    /// like a model or guardrail failure, is returned straight away.
+    pub async fn run_pipeline_with_plan(&self, goal: String) -> Result<PlanRun, Error> {
+        if self.agents.is_empty() {
+            return Err(Error::NoAgentsExist);
+        }
+
+        let mut report = UsageReport::new();
+        let mut steps: Vec<StepResult> = Vec::new();
+        let mut replans = 0;
+        let mut failure: Option<String> = None;
+
+        loop {
+            let plan = match self
+                .make_plan(&goal, &steps, failure.take().as_deref(), &mut report)
+                .await
+            {
+                Ok(plan) => plan,
+                Err(e @ (Error::InvalidStructuredOutput(_) | Error::EmptyPlan))
+                    if replans < self.max_replans =>
+                {
+                    failure = Some(format!("Your last plan couldn't be used: {e}"));
+                    replans += 1;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            for step in plan.steps {
+                match self.run_plan_step(&goal, &step, &steps, &mut report).await {
+                    Ok(output) => steps.push(StepResult {
+                        agent: step.agent,
+                        task: step.task,
+                        output,
+                    }),
+                    Err(e @ (Error::NoAgentNamed(_) | Error::InvalidPlanStep(_)))
+                        if replans < self.max_replans =>
+                    {
+                        failure = Some(format!(
+                            "The last step failed: {} was asked to \"{}\": {e}",
+                            step.agent, step.task
+                        ));
+                        break;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if failure.is_none() {
+                break;
+            }
+
+            replans += 1;
+        }
+
+        let output = steps
+            .last()
+            .map(|step| step.output.to_owned())
+            .unwrap_or_default();
+
+        Ok(PlanRun {
+            output,
+            steps,
+            replans,
+            report,
+        })
+    }
+
+    async fn make_plan(
+        &self,
+        goal: &str,
+        steps: &[StepResult],
+        failure: Option<&str>,
+        report: &mut UsageReport,
+    ) -> Result<Plan, Error> {
+        let request = plan_request(&self.planner, &self.agents, goal, steps, failure)
+            .with_settings(self.planner.settings().merge(&self.settings));
+
+        let res = self.model_for(&self.planner)?.chat(&request).await?;
+
+        report.record_completion(&self.planner.name(), &res, &self.prices);
+
+        let plan: Plan = parse_output(&res.content)?;
+
+        if plan.steps.is_empty() {
+            return Err(Error::EmptyPlan);
+        }
+
+        Ok(plan)
+    }
+
+    async fn run_plan_step(
+        &self,
+        goal: &str,
+        step: &PlanStep,
+        steps: &[StepResult],
+        report: &mut UsageReport,
     ) -> Result<String, Error> {
-        let mut context = data_source.retrieve_data().await?;
+        let agent = self
+            .agents
+            .iter()
+            .find(|x| x.name() == step.agent)
+            .ok_or_else(|| Error::NoAgentNamed(step.agent.to_owned()))?;
 
-        let mut agents = self.agents.iter().peekable();
+        if step.task.trim().is_empty() {
+            return Err(Error::InvalidPlanStep(format!(
+                "{} was given no task",
+                step.agent
+            )));
+        }
 
-        if agents.len() == 0 {
+        let res = self
+            .prompt_agent(
+                &step.task,
+                &[],
+                step_context(goal, steps),
+                &Blackboard::new(),
+                agent,
+            )
+            .await?;
+
+        report.record_completion(&agent.name(), &res, &self.prices);
+
+        Ok(res.content)
+    }
+
+    /// Runs the pipeline, streaming the last agent's output as it's generated. Earlier agents run
+    /// as normal; set `step_events` to also get an event when each of them starts and finishes.
+    pub fn run_pipeline_stream(
+        &self,
+        prompt: String,
+        step_events: bool,
+    ) -> BoxStream<'_, Result<PipelineEvent, Error>> {
+        let (tx, rx) = mpsc::unbounded();
+
+        let run = async move {
+            if let Err(e) = self.stream_steps(&prompt, step_events, &tx).await {
+                let _ = tx.unbounded_send(Err(e));
+            }
+        };
+
+        //The run sends its events down the channel and the channel closes when the run finishes
+        stream::select(rx, stream::once(run).filter_map(|_| future::ready(None))).boxed()
+    }
+
+    async fn stream_steps(
+        &self,
+        prompt: &str,
+        step_events: bool,
+        tx: &UnboundedSender<Result<PipelineEvent, Error>>,
+    ) -> Result<(), Error> {
+        let send = |event: PipelineEvent| {
+            let _ = tx.unbounded_send(Ok(event));
+        };
+
+        let Some((last, agents)) = self.agents.split_last() else {
             return Err(Error::NoAgentsExist);
+        };
+
+        let mut blackboard = Blackboard::new();
+        let mut report = UsageReport::new();
+        let mut previous = String::from("None");
+
+        for agent in agents {
+            if step_events {
+                send(PipelineEvent::StepStarted {
+                    agent: agent.name(),
+                });
+            }
+
+            let context = context_for(agent, &previous, &blackboard)?;
+            let res = self
+                .prompt_agent(prompt, &[], context, &blackboard, agent)
+                .await?;
+
+            report.record_completion(&agent.name(), &res, &self.prices);
+
+            let res = res.content;
+            blackboard.insert(&agent.writes(), &res)?;
+
+            if step_events {
+                send(PipelineEvent::StepFinished {
+                    agent: agent.name(),
+                    output: res.to_owned(),
+                });
+            }
+
+            previous = res;
         }
 
-        while let Some(agent) = agents.next() {
-            let res = model.prompt(&prompt, context.to_owned(), agent).await?;
+        if step_events {
+            send(PipelineEvent::StepStarted { agent: last.name() });
+        }
 
-            context = res.clone();
+        let context = context_for(last, &previous, &blackboard)?;
 
-            if agents.peek().is_none() {
-                return Ok(res);
+        //Output guardrails need the whole answer before any of it can be sent
+        let mut tokens = match self.output_guardrails_for(last).is_empty() {
+            true => {
+                let (request, model) = self
+                    .prepare_request(prompt, &[], &context, &blackboard, last)
+                    .await?;
+
+                model.chat_stream(&request).await?
+            }
+            false => single_response(
+                self.prompt_agent(prompt, &[], context, &blackboard, last)
+                    .await?,
+            ),
+        };
+
+        let mut output = String::new();
+        let mut usage = None;
+        let mut model = String::new();
+
+        while let Some(event) = tokens.next().await {
+            match event? {
+                StreamEvent::Delta(delta) => {
+                    output.push_str(&delta);
+                    send(PipelineEvent::Delta(delta));
+                }
+                StreamEvent::Done {
+                    model: done_model,
+                    usage: done_usage,
+                } => {
+                    model = done_model;
+                    usage = done_usage;
+                }
             }
         }
 
-        Err(Error::NoAgentsExist)
+        blackboard.insert(&last.writes(), &output)?;
+
+        if step_events {
+            send(PipelineEvent::StepFinished {
+                agent: last.name(),
+                output: output.to_owned(),
+            });
+        }
+
+        report.record(&last.name(), &model, usage, &self.prices);
+
+        send(PipelineEvent::Done {
+            output,
+            usage,
+            report,
+        });
+
+        Ok(())
     }
 
-    pub async fn run_agent_at_index_with_initial_data<P: PromptModel, D: DataSource>(
+    pub async fn run_agent_at_index_with_initial_data<D: DataSource>(
         &self,
         prompt: String,
         index: usize,
-        model: P,
         data_source: D,
     ) -> Result<String, Error> {
         let context = data_source.retrieve_data().await?;
+        let images = data_source.retrieve_images().await?;
 
         let agent = self.agents.get(index);
 
         match agent {
             Some(found_agent) => {
-                let res = model.prompt(&prompt, context, found_agent).await?;
+                let res = self
+                    .prompt_agent(&prompt, &images, context, &Blackboard::new(), found_agent)
+                    .await?;
 
-                Ok(res)
+                Ok(res.content)
             }
             None => Err(Error::NoAgentsExist),
         }
     }
 
-    pub async fn run_agent_by_name_with_initial_data<P: PromptModel, D: DataSource>(
+    pub async fn run_agent_by_name_with_initial_data<D: DataSource>(
         &self,
         prompt: String,
         name: &str,
-        model: P,
         data_source: D,
     ) -> Result<String, Error> {
         let context = data_source.retrieve_data().await?;
+        let images = data_source.retrieve_images().await?;
 
         let agent = self.agents.iter().find(|x| x.name() == *name);
 
         match agent {
             Some(found_agent) => {
-                let res = model.prompt(&prompt, context, found_agent).await?;
+                let res = self
+                    .prompt_agent(&prompt, &images, context, &Blackboard::new(), found_agent)
+                    .await?;
 
-                Ok(res)
+                Ok(res.content)
             }
             None => Err(Error::NoAgentsExist),
         }
@@ -131,3 +801,19 @@ impl Pipeline {
         self
     }
 }
+
+/// The context an agent is given: the blackboard keys it reads, or the previous agent's output if
+/// it doesn't read any.
+fn context_for(
+    agent: &Arc<dyn Agent>,
+    previous: &str,
+    blackboard: &Blackboard,
+) -> Result<String, Error> {
+    let reads = agent.reads();
+
+    if reads.is_empty() {
+        Ok(previous.to_owned())
+    } else {
+        blackboard.render(&reads)
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::agents::traits::Agent;
+use crate::models::{ChatRequest, Message, UsageReport};
+use crate::structured::OutputSchema;
+
+/// How many times a pipeline asks for a new plan after a step fails or a plan can't be used, by
+/// default.
+pub const DEFAULT_MAX_REPLANS: usize = 2;
+
+/// A list of steps for reaching a goal, each handled by one of the pipeline's agents.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct Plan {
+    pub steps: Vec<PlanStep>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct PlanStep {
+    /// The `name()` of the agent that carries out the step.
+    pub agent: String,
+    /// What the agent should do, written as an instruction to it.
+    pub task: String,
+}
+
+/// A step that has been carried out, with the agent's output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepResult {
+    pub agent: String,
+    pub task: String,
+    pub output: String,
+}
+
+/// The result of running a pipeline from a plan.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlanRun {
+    /// The output of the last step.
+    pub output: String,
+    /// Every step that succeeded, in the order they ran.
+    pub steps: Vec<StepResult>,
+    /// How many times the plan was remade after a step failed or a plan couldn't be used.
+    pub replans: usize,
+    /// The tokens used by the planner and every step.
+    pub report: UsageReport,
+}
+
+/// The request sent to the planner: its own system message, the agents it can pick from, and the
+/// goal with whatever has happened so far. `failure` says why the last plan was abandoned.
+pub(crate) fn plan_request(
+    planner: &Arc<dyn Agent>,
+    agents: &[Arc<dyn Agent>],
+    goal: &str,
+    steps: &[StepResult],
+    failure: Option<&str>,
+) -> ChatRequest {
+    let agents = agents
+        .iter()
+        .map(|agent| match agent.description() {
+            description if description.is_empty() => format!("- {}", agent.name()),
+            description => format!("- {}: {description}", agent.name()),
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let system_message = format!(
+        "{}
+
+The plan is carried out by a team of agents. Give each step to the agent best suited to it, and write its task as a clear instruction to that agent. Each agent is given the results of the steps before it, and the output of the last step is the final answer. If some steps have already been done, only plan the steps that are left. If something went wrong, plan around it rather than repeating it unchanged.
+
+The agents you can assign steps to are:
+{agents}
+
+Use the agent names exactly as written above. Reply with a JSON object with a `steps` array, where each step has an `agent` and a `task`.",
+        planner.system_message()
+    );
+
+    let mut context = vec![progress(steps)];
+    context.extend(failure.map(str::to_owned));
+
+    ChatRequest::new()
+        .with_agent(&planner.name())
+        .with_message(Message::system(&system_message))
+        .with_message(Message::user(goal).with_context(&context.join("\n\n")))
+        .with_output_schema(Some(OutputSchema::of::<Plan>()))
+}
+
+/// The context given to each step: the goal and the results of the steps before it.
+pub(crate) fn step_context(goal: &str, steps: &[StepResult]) -> String {
+    format!("The overall goal is: {goal}\n\n{}", progress(steps))
+}
+
+fn progress(steps: &[StepResult]) -> String {
+    if steps.is_empty() {
+        return String::from("No steps have been done yet.");
+    }
+
+    let steps = steps
+        .iter()
+        .enumerate()
+        .map(|(idx, step)| {
+            format!(
+                "Step {} ({}): {}\n{}",
+                idx + 1,
+                step.agent,
+                step.task,
+                step.output
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n");
+
+    format!("The steps done so far, and their results:\n\n{steps}")
+}
@@ -0,0 +1,363 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::errors::Error;
+use crate::models::batching::embed_in_batches;
+use crate::models::{
+    estimate_tokens, BatchLimits, ChatRequest, Completion, EmbedModel, EmbeddingResponse,
+    PromptModel, StreamEvent, TokenStream,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use tokio::time::Instant;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// A client-side limit on requests and tokens per minute.
+///
+/// Cloning a `RateLimiter` gives you a handle to the same underlying budget, so one limiter can be
+/// shared between every pipeline that talks to the same provider. Calls that would go over budget
+/// wait until there is room rather than failing.
+#[derive(Clone)]
+pub struct RateLimiter {
+    requests_per_minute: Option<usize>,
+    tokens_per_minute: Option<usize>,
+    max_retries: usize,
+    state: Arc<Mutex<RateLimiterState>>,
+}
+
+#[derive(Default)]
+struct RateLimiterState {
+    window: VecDeque<Reservation>,
+    paused_until: Option<Instant>,
+    next_id: u64,
+}
+
+//A request in the window. `tokens` starts as an estimate and is replaced by the real count once
+//the provider reports it.
+struct Reservation {
+    id: u64,
+    at: Instant,
+    tokens: usize,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self {
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            max_retries: 3,
+            state: Arc::new(Mutex::new(RateLimiterState::default())),
+        }
+    }
+
+    pub fn with_requests_per_minute(mut self, requests_per_minute: usize) -> Self {
+        self.requests_per_minute = Some(requests_per_minute);
+
+        self
+    }
+
+    pub fn requests_per_minute(&self) -> Option<usize> {
+        self.requests_per_minute
+    }
+
+    pub fn with_tokens_per_minute(mut self, tokens_per_minute: usize) -> Self {
+        self.tokens_per_minute = Some(tokens_per_minute);
+
+        self
+    }
+
+    pub fn tokens_per_minute(&self) -> Option<usize> {
+        self.tokens_per_minute
+    }
+
+    /// How many times a call that the provider rejected with `Error::RateLimited` is retried.
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+
+        self
+    }
+
+    pub fn max_retries(&self) -> usize {
+        self.max_retries
+    }
+
+    /// Waits until a request using `tokens` tokens fits in the budget, then records it.
+    pub async fn acquire(&self, tokens: usize) {
+        self.reserve(tokens).await;
+    }
+
+    /// Like `acquire`, but returns an id that `settle` can use to correct the token count.
+    async fn reserve(&self, tokens: usize) -> u64 {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+
+                while state
+                    .window
+                    .front()
+                    .is_some_and(|reservation| now.duration_since(reservation.at) >= WINDOW)
+                {
+                    state.window.pop_front();
+                }
+
+                match state.paused_until {
+                    Some(until) if until > now => Err(until - now),
+                    _ => {
+                        let requests_full = self
+                            .requests_per_minute
+                            .is_some_and(|limit| state.window.len() >= limit);
+
+                        let used_tokens: usize = state
+                            .window
+                            .iter()
+                            .map(|reservation| reservation.tokens)
+                            .sum();
+                        // A single request bigger than the whole budget is let through on an
+                        // empty window, otherwise it would wait forever.
+                        let tokens_full = self.tokens_per_minute.is_some_and(|limit| {
+                            !state.window.is_empty() && used_tokens + tokens > limit
+                        });
+
+                        match state.window.front() {
+                            Some(oldest) if requests_full || tokens_full => {
+                                Err(WINDOW.saturating_sub(now.duration_since(oldest.at)))
+                            }
+                            _ => {
+                                let id = state.next_id;
+                                state.next_id += 1;
+                                state.window.push_back(Reservation {
+                                    id,
+                                    at: now,
+                                    tokens,
+                                });
+
+                                Ok(id)
+                            }
+                        }
+                    }
+                }
+            };
+
+            match wait {
+                Ok(id) => return id,
+                Err(duration) => tokio::time::sleep(duration).await,
+            }
+        }
+    }
+
+    /// Replaces the estimate for a reserved request with the tokens it actually used.
+    fn settle(&self, id: u64, tokens: usize) {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(reservation) = state.window.iter_mut().find(|x| x.id == id) {
+            reservation.tokens = tokens;
+        }
+    }
+
+    /// Holds back every call made through this limiter (and its clones) for `duration`.
+    pub fn pause_for(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut state = self.state.lock().unwrap();
+
+        if state.paused_until.is_none_or(|current| current < until) {
+            state.paused_until = Some(until);
+        }
+    }
+
+    fn backoff(&self, attempt: usize, retry_after: Option<Duration>) -> Duration {
+        retry_after.unwrap_or_else(|| Duration::from_secs(1 << attempt.min(6)))
+    }
+}
+
+/// Wraps a model so that every prompt and embedding call goes through a `RateLimiter`.
+///
+/// If the wrapped model returns `Error::RateLimited`, the call is retried once the limiter has
+/// waited out the provider's `Retry-After` hint (or an exponential backoff if there wasn't one).
+#[derive(Clone)]
+pub struct RateLimited<M> {
+    model: M,
+    limiter: RateLimiter,
+}
+
+impl<M> RateLimited<M> {
+    pub fn new(model: M, limiter: RateLimiter) -> Self {
+        Self { model, limiter }
+    }
+
+    pub fn model(&self) -> &M {
+        &self.model
+    }
+
+    pub fn limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+}
+
+/// How many tokens an image is budgeted as. Providers charge by size, and this is about what a
+/// 1024x1024 image costs with OpenAI.
+pub const ESTIMATED_IMAGE_TOKENS: usize = 765;
+
+/// Estimates the tokens for every message in the request, budgeting each image as
+/// `ESTIMATED_IMAGE_TOKENS`, plus the most the answer can use if `max_tokens` is set.
+fn estimate_request_tokens(request: &ChatRequest) -> usize {
+    let prompt_tokens: usize = request
+        .messages()
+        .iter()
+        .map(|message| {
+            estimate_tokens(&message.text()) + message.images().len() * ESTIMATED_IMAGE_TOKENS
+        })
+        .sum();
+
+    prompt_tokens + request.settings().max_tokens.unwrap_or(0) as usize
+}
+
+#[async_trait]
+impl<M: PromptModel> PromptModel for RateLimited<M> {
+    fn supports_images(&self) -> bool {
+        self.model.supports_images()
+    }
+
+    async fn chat(&self, request: &ChatRequest) -> Result<Completion, Error> {
+        let tokens = estimate_request_tokens(request);
+
+        let mut attempt = 0;
+
+        loop {
+            let id = self.limiter.reserve(tokens).await;
+
+            match self.model.chat(request).await {
+                Err(Error::RateLimited { retry_after }) if attempt < self.limiter.max_retries => {
+                    self.limiter
+                        .pause_for(self.limiter.backoff(attempt, retry_after));
+                    attempt += 1;
+                }
+                res => {
+                    if let Some(usage) = res.as_ref().ok().and_then(|res| res.usage) {
+                        self.limiter.settle(id, usage.total_tokens as usize);
+                    }
+
+                    return res;
+                }
+            }
+        }
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<TokenStream, Error> {
+        let tokens = estimate_request_tokens(request);
+
+        let mut attempt = 0;
+
+        loop {
+            let id = self.limiter.reserve(tokens).await;
+
+            match self.model.chat_stream(request).await {
+                Err(Error::RateLimited { retry_after }) if attempt < self.limiter.max_retries => {
+                    self.limiter
+                        .pause_for(self.limiter.backoff(attempt, retry_after));
+                    attempt += 1;
+                }
+                Ok(stream) => {
+                    let limiter = self.limiter.clone();
+
+                    //The usage only comes in at the end of the stream
+                    let stream = stream.inspect(move |event| {
+                        if let Ok(StreamEvent::Done {
+                            usage: Some(usage), ..
+                        }) = event
+                        {
+                            limiter.settle(id, usage.total_tokens as usize);
+                        }
+                    });
+
+                    return Ok(Box::pin(stream));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<M: EmbedModel> RateLimited<M> {
+    async fn embed_batch(&self, batch: Vec<String>) -> anyhow::Result<EmbeddingResponse> {
+        let tokens = batch.iter().map(|x| estimate_tokens(x)).sum();
+
+        let mut attempt = 0;
+
+        loop {
+            let id = self.limiter.reserve(tokens).await;
+
+            match self.model.embed_file_with_usage(batch.to_owned()).await {
+                Err(err) if attempt < self.limiter.max_retries => match err.downcast_ref() {
+                    Some(Error::RateLimited { retry_after }) => {
+                        self.limiter
+                            .pause_for(self.limiter.backoff(attempt, *retry_after));
+                        attempt += 1;
+                    }
+                    _ => return Err(err),
+                },
+                res => {
+                    if let Some(usage) = res.as_ref().ok().and_then(|res| res.usage) {
+                        self.limiter.settle(id, usage.total_tokens as usize);
+                    }
+
+                    return res;
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<M: EmbedModel + Send + Sync> EmbedModel for RateLimited<M> {
+    fn batch_limits(&self) -> BatchLimits {
+        self.model.batch_limits()
+    }
+
+    async fn embed_file(&self, chunked_contents: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        let res = self.embed_file_with_usage(chunked_contents).await?;
+
+        Ok(res.embeddings)
+    }
+
+    async fn embed_file_with_usage(
+        &self,
+        chunked_contents: Vec<String>,
+    ) -> anyhow::Result<EmbeddingResponse> {
+        //Every batch is its own request to the provider, so each one goes through the limiter
+        embed_in_batches(chunked_contents, &self.model.batch_limits(), |batch| {
+            self.embed_batch(batch)
+        })
+        .await
+    }
+
+    async fn embed_sentence(&self, prompt: &str) -> anyhow::Result<Vec<f32>> {
+        let tokens = estimate_tokens(prompt);
+
+        let mut attempt = 0;
+
+        loop {
+            self.limiter.acquire(tokens).await;
+
+            match self.model.embed_sentence(prompt).await {
+                Err(err) if attempt < self.limiter.max_retries => match err.downcast_ref() {
+                    Some(Error::RateLimited { retry_after }) => {
+                        self.limiter
+                            .pause_for(self.limiter.backoff(attempt, *retry_after));
+                        attempt += 1;
+                    }
+                    _ => return Err(err),
+                },
+                res => return res,
+            }
+        }
+    }
+}
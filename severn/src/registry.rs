@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::agents::traits::Agent;
+use crate::errors::Error;
+use crate::models::ModelSettings;
+use crate::pipeline::Pipeline;
+use crate::templates::Template;
+use crate::tools::Tool;
+
+/// The formats that agent definitions can be written in. YAML needs the `yaml` feature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Picks the format from a file's extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// An agent written as config rather than Rust. `system_message` is a template, so it can use
+/// placeholders filled from `variables` or the run's blackboard. `tools` are the names of tools
+/// registered with `AgentRegistry::with_tool`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct AgentDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub system_message: String,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    #[serde(default)]
+    pub settings: ModelSettings,
+    #[serde(default)]
+    pub tools: Vec<String>,
+}
+
+/// An agent built from an `AgentDefinition`.
+pub struct ConfiguredAgent {
+    definition: AgentDefinition,
+    template: Template,
+    tools: Vec<Arc<dyn Tool>>,
+}
+
+impl ConfiguredAgent {
+    pub fn definition(&self) -> &AgentDefinition {
+        &self.definition
+    }
+}
+
+impl Agent for ConfiguredAgent {
+    fn name(&self) -> String {
+        self.definition.name.to_owned()
+    }
+
+    fn description(&self) -> String {
+        self.definition.description.to_owned()
+    }
+
+    fn system_message(&self) -> String {
+        self.template.fill(&self.definition.variables)
+    }
+
+    fn system_template(&self) -> Option<Template> {
+        Some(self.template.clone())
+    }
+
+    fn variables(&self) -> HashMap<String, String> {
+        self.definition.variables.clone()
+    }
+
+    fn settings(&self) -> ModelSettings {
+        self.definition.settings.clone()
+    }
+
+    fn tools(&self) -> Vec<Arc<dyn Tool>> {
+        self.tools.clone()
+    }
+}
+
+/// Agents that can be looked up by name, whether they were loaded from TOML, YAML or JSON files
+/// or registered from Rust.
+///
+/// Definitions are checked as they're added, so register any tools they use first.
+#[derive(Default)]
+pub struct AgentRegistry {
+    agents: HashMap<String, Arc<dyn Agent>>,
+    tools: HashMap<String, Arc<dyn Tool>>,
+    //Where each agent came from, so a duplicate name can point at both definitions
+    sources: HashMap<String, String>,
+}
+
+impl AgentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a tool available to definitions, under its `name()`.
+    pub fn with_tool(mut self, tool: Arc<dyn Tool>) -> Self {
+        self.tools.insert(tool.name(), tool);
+
+        self
+    }
+
+    pub fn register(self, agent: Arc<dyn Agent>) -> Result<Self, Error> {
+        self.insert(agent, "Rust")
+    }
+
+    pub fn with_definition(self, definition: AgentDefinition) -> Result<Self, Error> {
+        self.insert_definition(definition, "Rust")
+    }
+
+    /// Loads the definitions in `contents`, which can be a single definition or a list of them
+    /// under `agents`.
+    pub fn load_str(self, contents: &str, format: ConfigFormat) -> Result<Self, Error> {
+        self.load_from(contents, format, "a string")
+    }
+
+    /// Loads a `.toml`, `.yaml`, `.yml` or `.json` file.
+    pub fn load_file<P: AsRef<Path>>(self, path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+
+        let Some(format) = ConfigFormat::from_path(path) else {
+            return Err(Error::InvalidAgentDefinition(format!(
+                "{} isn't a TOML, YAML or JSON file",
+                path.display()
+            )));
+        };
+
+        let contents = std::fs::read_to_string(path)?;
+
+        self.load_from(&contents, format, &path.display().to_string())
+            .map_err(|e| match e {
+                Error::InvalidAgentDefinition(message) => {
+                    Error::InvalidAgentDefinition(format!("{}: {message}", path.display()))
+                }
+                other => other,
+            })
+    }
+
+    /// Loads every TOML, YAML and JSON file in the directory, in name order. Other files and
+    /// subdirectories are skipped.
+    pub fn load_dir<P: AsRef<Path>>(self, path: P) -> Result<Self, Error> {
+        let mut paths = std::fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, std::io::Error>>()?;
+
+        paths.sort();
+
+        paths
+            .into_iter()
+            .filter(|path| path.is_file() && ConfigFormat::from_path(path).is_some())
+            .try_fold(self, |registry, path| registry.load_file(path))
+    }
+
+    pub fn get(&self, name: &str) -> Result<Arc<dyn Agent>, Error> {
+        self.agents
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::NoAgentNamed(name.to_owned()))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.agents.contains_key(name)
+    }
+
+    /// The names of every agent, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self.agents.keys().cloned().collect::<Vec<String>>();
+        names.sort();
+
+        names
+    }
+
+    pub fn len(&self) -> usize {
+        self.agents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+    }
+
+    /// A pipeline with the named agents, in order. Set a model on it before running it.
+    pub fn pipeline(&self, names: &[&str]) -> Result<Pipeline, Error> {
+        names.iter().try_fold(Pipeline::new(), |pipeline, name| {
+            Ok(pipeline.add_agent(self.get(name)?))
+        })
+    }
+
+    fn insert(mut self, agent: Arc<dyn Agent>, source: &str) -> Result<Self, Error> {
+        let name = agent.name();
+
+        if let Some(first) = self.sources.get(&name) {
+            return Err(Error::DuplicateAgent {
+                name,
+                first: first.to_owned(),
+                second: source.to_owned(),
+            });
+        }
+
+        self.sources.insert(name.to_owned(), source.to_owned());
+        self.agents.insert(name, agent);
+
+        Ok(self)
+    }
+
+    fn insert_definition(self, definition: AgentDefinition, source: &str) -> Result<Self, Error> {
+        let tools = definition
+            .tools
+            .iter()
+            .map(|name| {
+                self.tools.get(name).cloned().ok_or_else(|| {
+                    Error::InvalidAgentDefinition(format!(
+                        "agent {} uses the tool {name}, which hasn't been registered",
+                        definition.name
+                    ))
+                })
+            })
+            .collect::<Result<Vec<Arc<dyn Tool>>, Error>>()?;
+
+        let agent = ConfiguredAgent {
+            template: Template::new(&definition.system_message),
+            definition,
+            tools,
+        };
+
+        self.insert(Arc::new(agent), source)
+    }
+
+    fn load_from(self, contents: &str, format: ConfigFormat, source: &str) -> Result<Self, Error> {
+        //Every format is read into JSON first, so the definitions are checked the same way
+        let value: Value = match format {
+            ConfigFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string()),
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| e.to_string()),
+            #[cfg(not(feature = "yaml"))]
+            ConfigFormat::Yaml => Err(String::from(
+                "loading YAML needs the `yaml` feature to be enabled",
+            )),
+            ConfigFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string()),
+        }
+        .map_err(Error::InvalidAgentDefinition)?;
+
+        let definitions = match value.get("agents") {
+            Some(agents) => Vec::<AgentDefinition>::deserialize(agents),
+            None => AgentDefinition::deserialize(&value).map(|definition| vec![definition]),
+        }
+        .map_err(|e| Error::InvalidAgentDefinition(e.to_string()))?;
+
+        definitions
+            .into_iter()
+            .try_fold(self, |registry, definition| {
+                registry.insert_definition(definition, source)
+            })
+    }
+}
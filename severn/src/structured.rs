@@ -0,0 +1,124 @@
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::errors::Error;
+
+/// A JSON schema that an agent's output has to match.
+///
+/// Models with native support (OpenAI's `json_schema` response format, Ollama's `format`) are
+/// sent the schema directly. Everything else gets the schema as instructions in the system
+/// message, and the output is validated once it comes back.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputSchema {
+    name: String,
+    schema: Value,
+}
+
+impl OutputSchema {
+    pub fn new(name: &str, schema: Value) -> Self {
+        Self {
+            name: sanitise_name(name),
+            schema,
+        }
+    }
+
+    /// Generates the schema from a Rust type.
+    pub fn of<T: JsonSchema>() -> Self {
+        let schema = schemars::schema_for!(T);
+
+        Self::new(
+            &T::schema_name(),
+            serde_json::to_value(schema).unwrap_or_default(),
+        )
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    /// Instructions for models that can't be sent the schema natively.
+    pub fn instructions(&self) -> String {
+        format!(
+            "Respond only with a JSON value that matches the following JSON schema. Do not wrap it in a code block or add any other text.\n\n{}",
+            serde_json::to_string_pretty(&self.schema).unwrap_or_default()
+        )
+    }
+
+    /// Parses the output as JSON and checks it against the top level of the schema: the type,
+    /// and that any required properties are present.
+    pub fn validate(&self, output: &str) -> Result<Value, Error> {
+        let value: Value = serde_json::from_str(strip_code_fence(output))
+            .map_err(|e| Error::InvalidStructuredOutput(e.to_string()))?;
+
+        let expected_type = self.schema.get("type").and_then(Value::as_str);
+
+        if let Some(expected_type) = expected_type {
+            if !matches_type(&value, expected_type) {
+                return Err(Error::InvalidStructuredOutput(format!(
+                    "expected a JSON {expected_type}"
+                )));
+            }
+        }
+
+        if let Some(required) = self.schema.get("required").and_then(Value::as_array) {
+            for key in required.iter().filter_map(Value::as_str) {
+                if value.get(key).is_none() {
+                    return Err(Error::InvalidStructuredOutput(format!(
+                        "missing required property `{key}`"
+                    )));
+                }
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+/// Deserializes an agent's output into `T`, ignoring any Markdown code fence around it.
+pub fn parse_output<T: DeserializeOwned>(output: &str) -> Result<T, Error> {
+    serde_json::from_str(strip_code_fence(output))
+        .map_err(|e| Error::InvalidStructuredOutput(e.to_string()))
+}
+
+fn strip_code_fence(output: &str) -> &str {
+    let trimmed = output.trim();
+
+    match trimmed.strip_prefix("```") {
+        Some(rest) => {
+            // Skip the language tag, if there is one
+            let rest = rest.split_once('\n').map_or(rest, |(_, body)| body);
+
+            rest.trim_end().strip_suffix("```").unwrap_or(rest).trim()
+        }
+        None => trimmed,
+    }
+}
+
+fn matches_type(value: &Value, expected_type: &str) -> bool {
+    match expected_type {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+// OpenAI only accepts names made of a-z, A-Z, 0-9, underscores and dashes
+fn sanitise_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+            _ => '_',
+        })
+        .take(64)
+        .collect()
+}
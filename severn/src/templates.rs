@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+/// A system message with named placeholders, such as `Write for {audience} in {locale}.`, that are
+/// filled in when the agent is called.
+///
+/// Placeholder names can contain letters, numbers, `_`, `-` and `.`. Use `{{` and `}}` for literal
+/// braces. Any other brace is kept as written, so text like JSON examples doesn't need escaping
+/// unless it looks like a placeholder.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Text(String),
+    Variable(String),
+}
+
+impl Template {
+    pub fn new(template: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut rest = template;
+
+        while let Some(c) = rest.chars().next() {
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                text.push(c);
+                rest = &rest[2..];
+                continue;
+            }
+
+            if c == '{' {
+                if let Some((name, after)) = placeholder(rest) {
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+
+                    parts.push(Part::Variable(name.to_owned()));
+                    rest = after;
+                    continue;
+                }
+            }
+
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Self { parts }
+    }
+
+    /// The names of the placeholders, in the order they first appear.
+    pub fn variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = Vec::new();
+
+        for part in &self.parts {
+            if let Part::Variable(name) = part {
+                if !variables.contains(name) {
+                    variables.push(name.to_owned());
+                }
+            }
+        }
+
+        variables
+    }
+
+    /// The placeholders that `values` has nothing for.
+    pub fn missing_variables(&self, values: &HashMap<String, String>) -> Vec<String> {
+        self.variables()
+            .into_iter()
+            .filter(|name| !values.contains_key(name))
+            .collect()
+    }
+
+    /// Fills in the placeholders from `values`. Placeholders with no value are left as written,
+    /// so check `missing_variables` first if they need to be reported.
+    pub fn fill(&self, values: &HashMap<String, String>) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.to_owned(),
+                Part::Variable(name) => match values.get(name) {
+                    Some(value) => value.to_owned(),
+                    None => format!("{{{name}}}"),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Splits `{name}` off the front of `text`, if it starts with a placeholder.
+fn placeholder(text: &str) -> Option<(&str, &str)> {
+    let end = text.find('}')?;
+    let name = &text[1..end];
+
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'));
+
+    valid.then(|| (name, &text[end + 1..]))
+}
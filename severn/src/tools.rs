@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::errors::Error;
+
+/// The most times a model can ask for tools to be called before giving a final answer.
+pub const MAX_TOOL_ITERATIONS: usize = 10;
+
+/// Something an agent can call to look information up or take an action.
+#[async_trait]
+pub trait Tool: Send + Sync {
+    fn name(&self) -> String;
+    fn description(&self) -> String;
+
+    /// A JSON schema describing the arguments that `call` expects.
+    fn parameters(&self) -> Value;
+
+    async fn call(&self, arguments: Value) -> Result<String, Error>;
+}
+
+/// Runs the tool that the model asked for and returns the result to send back to it.
+///
+/// Unknown tools, arguments that aren't valid JSON and tool errors are all reported back to the
+/// model as the result, so it gets a chance to correct itself instead of the whole run failing.
+pub(crate) async fn call_tool(tools: &[Arc<dyn Tool>], name: &str, arguments: Value) -> String {
+    let Some(tool) = tools.iter().find(|tool| tool.name() == name) else {
+        return format!("Error: there is no tool called {name}");
+    };
+
+    match tool.call(arguments).await {
+        Ok(res) => res,
+        Err(e) => format!("Error: {e}"),
+    }
+}
+
+/// Parses tool arguments that were sent as a JSON string. Empty arguments are treated as an empty
+/// object.
+pub(crate) fn parse_arguments(arguments: &str) -> Result<Value, String> {
+    if arguments.trim().is_empty() {
+        return Ok(Value::Object(Default::default()));
+    }
+
+    serde_json::from_str(arguments)
+        .map_err(|e| format!("Error: the arguments were not valid JSON: {e}"))
+}
@@ -0,0 +1,255 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+use severn::errors::Error;
+use severn::models::{
+    Anthropic, ChatRequest, Image, Message, ModelSettings, PromptModel, StreamEvent, Usage,
+};
+
+mod common;
+
+use common::server::{MockResponse, MockServer};
+use common::Weather;
+
+fn model(server: &MockServer) -> Anthropic {
+    Anthropic::from_api_key("test-key")
+        .unwrap()
+        .with_base_url(server.url())
+        .with_model("claude-test")
+}
+
+fn reply(content: Value) -> MockResponse {
+    MockResponse::json(json!({
+        "id": "msg_1",
+        "type": "message",
+        "role": "assistant",
+        "content": content,
+        "usage": {"input_tokens": 10, "output_tokens": 5}
+    }))
+}
+
+#[tokio::test]
+async fn requests_use_the_messages_api_format() {
+    let server = MockServer::start().await;
+    server.respond(reply(json!([{"type": "text", "text": "Hello"}])));
+
+    let request = ChatRequest::new()
+        .with_agent("Greeter")
+        .with_system_message("You are Greeter")
+        .with_message(Message::user("Hi").with_image(Image::from_bytes(b"wave", "image/png")))
+        .with_message(Message::user("Are you there?"))
+        .with_settings(
+            ModelSettings::new()
+                .with_temperature(0.2)
+                .with_max_tokens(50),
+        );
+
+    let res = model(&server).chat(&request).await.unwrap();
+
+    assert_eq!(res.content, "Hello");
+    assert_eq!(res.model, "claude-test");
+    assert_eq!(res.usage, Some(Usage::new(10, 5)));
+
+    let sent = &server.requests()[0];
+    let body = sent.json();
+
+    assert_eq!(sent.method, "POST");
+    assert_eq!(sent.path, "/v1/messages");
+    assert_eq!(sent.header("x-api-key"), Some("test-key"));
+    assert_eq!(sent.header("anthropic-version"), Some("2023-06-01"));
+
+    assert_eq!(body["model"], "claude-test");
+    assert_eq!(body["max_tokens"], 50);
+    assert_eq!(body["temperature"], 0.2);
+    assert!(body.get("stream").is_none());
+    assert!(body.get("tools").is_none());
+
+    //The system message is sent on its own, and the two user messages are merged into one
+    assert_eq!(body["system"], "You are Greeter");
+    assert_eq!(
+        body["messages"],
+        json!([{
+            "role": "user",
+            "content": [
+                {
+                    "type": "image",
+                    "source": {"type": "base64", "media_type": "image/png", "data": "d2F2ZQ=="}
+                },
+                {"type": "text", "text": "Hi"},
+                {"type": "text", "text": "Are you there?"}
+            ]
+        }])
+    );
+}
+
+#[tokio::test]
+async fn replies_without_text_are_an_error() {
+    let server = MockServer::start().await;
+    server.respond(reply(json!([])));
+
+    let request = ChatRequest::new()
+        .with_agent("Greeter")
+        .with_message(Message::user("Hi"));
+
+    let res = model(&server).chat(&request).await;
+
+    assert!(matches!(res, Err(Error::OptionIsNone)));
+}
+
+#[tokio::test]
+async fn tool_calls_are_sent_back_as_tool_results() {
+    let server = MockServer::start().await;
+    server
+        .respond(reply(json!([
+            {"type": "text", "text": "Let me check."},
+            {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "Bristol"}}
+        ])))
+        .respond(reply(json!([{"type": "text", "text": "It's sunny."}])));
+
+    let request = ChatRequest::new()
+        .with_agent("Weather")
+        .with_message(Message::user("What's the weather in Bristol?"))
+        .with_tools(vec![Arc::new(Weather)]);
+
+    let res = model(&server).chat(&request).await.unwrap();
+
+    assert_eq!(res.content, "It's sunny.");
+    assert_eq!(res.usage, Some(Usage::new(20, 10)));
+
+    let requests = server.requests();
+    let first = requests[0].json();
+    let second = requests[1].json();
+
+    assert_eq!(first["tools"][0]["name"], "get_weather");
+    assert_eq!(
+        first["tools"][0]["input_schema"]["required"],
+        json!(["city"])
+    );
+
+    assert_eq!(
+        second["messages"],
+        json!([
+            {"role": "user", "content": [{"type": "text", "text": "What's the weather in Bristol?"}]},
+            {"role": "assistant", "content": [
+                {"type": "text", "text": "Let me check."},
+                {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "Bristol"}}
+            ]},
+            {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_1", "content": "It's sunny in Bristol"}
+            ]}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn rate_limits_come_back_with_the_retry_after_hint() {
+    let server = MockServer::start().await;
+    server.respond(
+        MockResponse::json(json!({
+            "type": "error",
+            "error": {"type": "rate_limit_error", "message": "Slow down"}
+        }))
+        .with_status(429)
+        .with_header("retry-after", "7"),
+    );
+
+    let res = model(&server)
+        .chat(&ChatRequest::new().with_message(Message::user("Hi")))
+        .await;
+
+    assert!(matches!(
+        res,
+        Err(Error::RateLimited { retry_after: Some(retry_after) })
+            if retry_after == Duration::from_secs(7)
+    ));
+}
+
+#[tokio::test]
+async fn other_errors_carry_the_provider_message() {
+    let server = MockServer::start().await;
+    server.respond(
+        MockResponse::json(json!({
+            "type": "error",
+            "error": {"type": "invalid_request_error", "message": "max_tokens is too large"}
+        }))
+        .with_status(400),
+    );
+
+    let res = model(&server)
+        .chat(&ChatRequest::new().with_message(Message::user("Hi")))
+        .await;
+
+    assert!(matches!(
+        res,
+        Err(Error::ProviderError { status: 400, message }) if message == "max_tokens is too large"
+    ));
+}
+
+#[tokio::test]
+async fn streams_text_deltas_and_usage() {
+    let events = [
+        json!({"type": "message_start", "message": {"usage": {"input_tokens": 12, "output_tokens": 1}}}),
+        json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        json!({"type": "ping"}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hel"}}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "lo"}}),
+        json!({"type": "content_block_stop", "index": 0}),
+        json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 4}}),
+        json!({"type": "message_stop"}),
+    ]
+    .iter()
+    .map(|event| format!("event: {}\ndata: {event}", event["type"].as_str().unwrap()))
+    .collect::<Vec<String>>();
+
+    let server = MockServer::start().await;
+    server.respond(MockResponse::sse(&events));
+
+    let request = ChatRequest::new()
+        .with_system_message("You are Greeter")
+        .with_message(Message::user("Hi"));
+
+    let events = model(&server)
+        .chat_stream(&request)
+        .await
+        .unwrap()
+        .map(|event| event.unwrap())
+        .collect::<Vec<StreamEvent>>()
+        .await;
+
+    assert_eq!(
+        events,
+        vec![
+            StreamEvent::Delta("Hel".into()),
+            StreamEvent::Delta("lo".into()),
+            StreamEvent::Done {
+                model: "claude-test".into(),
+                usage: Some(Usage::new(12, 4)),
+            },
+        ]
+    );
+
+    let body = server.requests()[0].json();
+
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["system"], "You are Greeter");
+}
+
+#[tokio::test]
+async fn stream_errors_are_returned() {
+    let server = MockServer::start().await;
+    server.respond(MockResponse::sse(&[format!(
+        "event: error\ndata: {}",
+        json!({"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}})
+    )]));
+
+    let mut stream = model(&server)
+        .chat_stream(&ChatRequest::new().with_message(Message::user("Hi")))
+        .await
+        .unwrap();
+
+    let res = stream.next().await.unwrap();
+
+    assert!(matches!(res, Err(Error::ProviderError { message, .. }) if message == "Overloaded"));
+}
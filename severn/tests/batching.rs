@@ -0,0 +1,53 @@
+use severn::models::{estimate_tokens, BatchLimits};
+
+fn chunks(count: usize, len: usize) -> Vec<String> {
+    (0..count).map(|idx| format!("{idx:0len$}")).collect()
+}
+
+#[test]
+fn splits_on_item_limit_in_order() {
+    let input = chunks(10, 4);
+    let batches = BatchLimits::new().with_max_items(4).split(input.clone());
+
+    assert_eq!(
+        batches.iter().map(Vec::len).collect::<Vec<usize>>(),
+        vec![4, 4, 2]
+    );
+    assert_eq!(batches.concat(), input);
+}
+
+#[test]
+fn splits_on_token_limit() {
+    // Each chunk is 40 characters, which is estimated at 10 tokens
+    let input = chunks(5, 40);
+    let batches = BatchLimits::new().with_max_tokens(25).split(input.clone());
+
+    assert_eq!(
+        batches.iter().map(Vec::len).collect::<Vec<usize>>(),
+        vec![2, 2, 1]
+    );
+    assert_eq!(batches.concat(), input);
+}
+
+#[test]
+fn oversized_chunks_get_their_own_batch() {
+    let input = vec!["a".repeat(400), "b".into(), "c".into()];
+    let batches = BatchLimits::new().with_max_tokens(10).split(input);
+
+    assert_eq!(
+        batches.iter().map(Vec::len).collect::<Vec<usize>>(),
+        vec![1, 2]
+    );
+}
+
+#[test]
+fn empty_input_has_no_batches() {
+    assert!(BatchLimits::new().split(Vec::new()).is_empty());
+}
+
+#[test]
+fn tokens_are_estimated_from_characters() {
+    assert_eq!(estimate_tokens(""), 0);
+    assert_eq!(estimate_tokens("rivers"), 2);
+    assert_eq!(estimate_tokens("afon ŵy"), 2);
+}
@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use serde_json::json;
+use severn::agents::traits::Agent;
+use severn::blackboard::Blackboard;
+use severn::errors::Error;
+use severn::models::MockModel;
+use severn::pipeline::Pipeline;
+
+#[test]
+fn values_round_trip_through_json() {
+    let mut blackboard = Blackboard::new().with_value("topic", "rivers").unwrap();
+
+    blackboard
+        .insert("sources", vec!["atlas", "survey"])
+        .unwrap();
+
+    assert_eq!(
+        blackboard.get::<String>("topic").unwrap(),
+        Some("rivers".into())
+    );
+    assert_eq!(
+        blackboard.get::<Vec<String>>("sources").unwrap(),
+        Some(vec!["atlas".into(), "survey".into()])
+    );
+    assert_eq!(blackboard.get::<String>("missing").unwrap(), None);
+    assert!(blackboard.get::<u32>("topic").is_err());
+    assert_eq!(blackboard.len(), 2);
+
+    assert_eq!(blackboard.remove("topic"), Some(json!("rivers")));
+    assert!(!blackboard.contains_key("topic"));
+}
+
+#[test]
+fn text_is_plain_for_strings_and_json_for_everything_else() {
+    let blackboard = Blackboard::new()
+        .with_value("topic", "rivers")
+        .unwrap()
+        .with_value("lengths", json!({"Severn": 354}))
+        .unwrap();
+
+    assert_eq!(blackboard.get_text("topic"), Some("rivers".into()));
+    assert_eq!(
+        blackboard.get_text("lengths"),
+        Some("{\n  \"Severn\": 354\n}".into())
+    );
+}
+
+#[test]
+fn render_labels_each_key_and_fails_on_missing_ones() {
+    let blackboard = Blackboard::new()
+        .with_value("topic", "rivers")
+        .unwrap()
+        .with_value("outline", "1. Source\n2. Mouth")
+        .unwrap();
+
+    let rendered = blackboard
+        .render(&["topic".into(), "outline".into()])
+        .unwrap();
+
+    assert_eq!(
+        rendered,
+        "{topic}:\nrivers\n\n{outline}:\n1. Source\n2. Mouth"
+    );
+
+    let res = blackboard.render(&["topic".into(), "draft".into()]);
+
+    assert!(matches!(res, Err(Error::BlackboardKeyMissing(key)) if key == "draft"));
+}
+
+struct Reader {
+    name: &'static str,
+    reads: Vec<String>,
+}
+
+impl Agent for Reader {
+    fn name(&self) -> String {
+        self.name.into()
+    }
+
+    fn system_message(&self) -> String {
+        format!("You are {}", self.name)
+    }
+
+    fn reads(&self) -> Vec<String> {
+        self.reads.to_owned()
+    }
+}
+
+fn reader(name: &'static str, reads: &[&str]) -> Arc<dyn Agent> {
+    Arc::new(Reader {
+        name,
+        reads: reads.iter().map(|x| x.to_string()).collect(),
+    })
+}
+
+#[tokio::test]
+async fn pipeline_agents_read_and_write_the_blackboard() {
+    let model = Arc::new(
+        MockModel::new()
+            .with_agent_response("Researcher", "Deltas")
+            .with_default_response("Rivers flow."),
+    );
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .add_agent(reader("Researcher", &[]))
+        .add_agent(reader("Writer", &["topic", "Researcher"]));
+
+    let blackboard = pipeline
+        .run_pipeline_with_blackboard(
+            "Write about rivers".into(),
+            Blackboard::new().with_value("topic", "rivers").unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(blackboard.get_text("Researcher"), Some("Deltas".into()));
+    assert_eq!(
+        model.calls_for("Writer")[0].data,
+        "{topic}:\nrivers\n\n{Researcher}:\nDeltas"
+    );
+
+    let res = Pipeline::new()
+        .with_model(model)
+        .add_agent(reader("Writer", &["draft"]))
+        .run_pipeline("Write about rivers".into())
+        .await;
+
+    assert!(matches!(res, Err(Error::BlackboardKeyMissing(key)) if key == "draft"));
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use severn::models::{
+    ChatRequest, ContentPart, Image, Message, MockModel, ModelSettings, PromptModel, Role, ToolCall,
+};
+use severn::pipeline::Pipeline;
+
+mod common;
+
+use common::TestAgent;
+
+#[test]
+fn messages_keep_their_parts_in_order() {
+    let image = Image::from_bytes(b"chart", "image/png");
+    let message = Message::user("Describe this")
+        .with_image(image.clone())
+        .with_text("Be brief");
+
+    assert_eq!(message.role(), Role::User);
+    assert_eq!(
+        message.content(),
+        &[
+            ContentPart::Text("Describe this".into()),
+            ContentPart::Image(image.clone()),
+            ContentPart::Text("Be brief".into()),
+        ]
+    );
+    assert_eq!(message.text(), "Describe this\n\nBe brief");
+    assert_eq!(message.images(), vec![&image]);
+
+    let result = Message::tool_result("call_1", "22 degrees");
+
+    assert_eq!(result.role(), Role::Tool);
+    assert_eq!(result.tool_call_id(), Some("call_1"));
+}
+
+#[test]
+fn agent_requests_dont_escape_the_context() {
+    let request = ChatRequest::for_agent(
+        &TestAgent::new("Reader")
+            .with_settings(ModelSettings::new().with_temperature(0.5))
+            .build(),
+        "Summarise",
+        "line one\nline \"two\"",
+    );
+
+    let messages = request.messages();
+
+    assert_eq!(request.agent(), "Reader");
+    assert_eq!(request.settings().temperature, Some(0.5));
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0], Message::system("You are Reader"));
+    assert_eq!(messages[1].role(), Role::User);
+    assert!(messages[1].text().contains("line one\nline \"two\""));
+}
+
+#[tokio::test]
+async fn models_are_sent_the_whole_conversation() {
+    let model = MockModel::new().with_agent_response("Weather", "It's still sunny");
+
+    let request = ChatRequest::new()
+        .with_agent("Weather")
+        .with_message(Message::system("You report the weather"))
+        .with_message(Message::user("What's the weather in Paris?"))
+        .with_message(
+            Message::new(Role::Assistant).with_tool_calls(vec![ToolCall {
+                id: "call_1".into(),
+                name: "weather".into(),
+                arguments: serde_json::json!({ "city": "Paris" }),
+            }]),
+        )
+        .with_message(Message::tool_result("call_1", "Sunny"))
+        .with_message(Message::assistant("It's sunny in Paris."))
+        .with_message(Message::user("And now?"));
+
+    let res = model.chat(&request).await.unwrap();
+
+    assert_eq!(res.content, "It's still sunny");
+
+    let call = &model.calls()[0];
+
+    assert_eq!(call.agent, "Weather");
+    assert_eq!(call.prompt, "And now?");
+    assert_eq!(call.data, "");
+    assert_eq!(call.messages, request.messages());
+}
+
+#[tokio::test]
+async fn pipelines_send_the_system_message_and_context() {
+    let model = Arc::new(MockModel::new().with_default_response("done"));
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .with_settings(ModelSettings::new().with_max_tokens(100))
+        .add_agent(
+            TestAgent::new("Reader")
+                .with_settings(ModelSettings::new().with_temperature(0.5))
+                .build(),
+        );
+
+    pipeline
+        .run_pipeline("Summarise the notes".into())
+        .await
+        .unwrap();
+
+    let call = &model.calls()[0];
+
+    assert_eq!(call.messages[0], Message::system("You are Reader"));
+    assert_eq!(call.prompt, "Summarise the notes");
+    assert_eq!(call.data, "None");
+    assert_eq!(call.settings.temperature, Some(0.5));
+    assert_eq!(call.settings.max_tokens, Some(100));
+}
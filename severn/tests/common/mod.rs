@@ -0,0 +1,136 @@
+//Not every test file uses every part of the fixture
+#![allow(dead_code)]
+
+pub mod server;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use severn::agents::traits::Agent;
+use severn::errors::Error;
+use severn::guardrails::Guardrail;
+use severn::models::ModelSettings;
+use severn::tools::Tool;
+
+use server::MockResponse;
+
+/// An agent for tests. Its system message is "You are {name}".
+pub struct TestAgent {
+    name: &'static str,
+    description: &'static str,
+    settings: ModelSettings,
+    reads: Vec<String>,
+    output_guardrails: Vec<Arc<dyn Guardrail>>,
+}
+
+impl TestAgent {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            description: "",
+            settings: ModelSettings::new(),
+            reads: Vec::new(),
+            output_guardrails: Vec::new(),
+        }
+    }
+
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = description;
+
+        self
+    }
+
+    pub fn with_settings(mut self, settings: ModelSettings) -> Self {
+        self.settings = settings;
+
+        self
+    }
+
+    pub fn with_reads(mut self, reads: &[&str]) -> Self {
+        self.reads = reads.iter().map(|x| x.to_string()).collect();
+
+        self
+    }
+
+    pub fn with_output_guardrail(mut self, guardrail: Arc<dyn Guardrail>) -> Self {
+        self.output_guardrails.push(guardrail);
+
+        self
+    }
+
+    pub fn build(self) -> Arc<dyn Agent> {
+        Arc::new(self)
+    }
+}
+
+impl Agent for TestAgent {
+    fn name(&self) -> String {
+        self.name.into()
+    }
+
+    fn description(&self) -> String {
+        self.description.into()
+    }
+
+    fn system_message(&self) -> String {
+        format!("You are {}", self.name)
+    }
+
+    fn settings(&self) -> ModelSettings {
+        self.settings.clone()
+    }
+
+    fn reads(&self) -> Vec<String> {
+        self.reads.to_owned()
+    }
+
+    fn output_guardrails(&self) -> Vec<Arc<dyn Guardrail>> {
+        self.output_guardrails.clone()
+    }
+}
+
+pub fn agent(name: &'static str) -> Arc<dyn Agent> {
+    TestAgent::new(name).build()
+}
+
+/// A tool that says it's sunny in whichever city it's asked about.
+pub struct Weather;
+
+#[async_trait]
+impl Tool for Weather {
+    fn name(&self) -> String {
+        "get_weather".into()
+    }
+
+    fn description(&self) -> String {
+        "Gets the current weather for a city.".into()
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": { "city": { "type": "string" } },
+            "required": ["city"]
+        })
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String, Error> {
+        Ok(format!(
+            "It's sunny in {}",
+            arguments["city"].as_str().unwrap_or("nowhere")
+        ))
+    }
+}
+
+/// A chat completion from the OpenAI API, with `message` as the reply.
+pub fn openai_reply(message: Value) -> MockResponse {
+    MockResponse::json(json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 0,
+        "model": "gpt-4o",
+        "choices": [{"index": 0, "message": message, "finish_reason": "stop"}],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+    }))
+}
@@ -0,0 +1,207 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A request the server received.
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// The path, including any query string.
+    pub path: String,
+    /// Header names are lowercase.
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+/// A response for the server to send.
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl MockResponse {
+    pub fn json(body: Value) -> Self {
+        Self::text("application/json", &body.to_string())
+    }
+
+    pub fn text(content_type: &str, body: &str) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("content-type".into(), content_type.into())],
+            body: body.to_owned(),
+        }
+    }
+
+    /// Server-sent events, each given as its full text, for example `data: {...}`.
+    pub fn sse(events: &[String]) -> Self {
+        let body = events
+            .iter()
+            .map(|event| format!("{event}\n\n"))
+            .collect::<String>();
+
+        Self::text("text/event-stream", &body)
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.into(), value.into()));
+
+        self
+    }
+}
+
+/// An HTTP server on localhost that sends back queued responses in order and records every
+/// request, so providers can be tested without a network connection. Once the queue is empty,
+/// it answers with a 500.
+pub struct MockServer {
+    url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    responses: Arc<Mutex<VecDeque<MockResponse>>>,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let responses = Arc::new(Mutex::new(VecDeque::new()));
+
+        let server = Self {
+            url,
+            requests: requests.clone(),
+            responses: responses.clone(),
+        };
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let requests = requests.clone();
+                let responses = responses.clone();
+
+                tokio::spawn(async move { handle(stream, requests, responses).await });
+            }
+        });
+
+        server
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn respond(&self, response: MockResponse) -> &Self {
+        self.responses.lock().unwrap().push_back(response);
+
+        self
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn handle(
+    mut stream: TcpStream,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    responses: Arc<Mutex<VecDeque<MockResponse>>>,
+) {
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+
+    requests.lock().unwrap().push(request);
+
+    let response = responses.lock().unwrap().pop_front().unwrap_or_else(|| {
+        MockResponse::json(serde_json::json!({"error": "no response queued"})).with_status(500)
+    });
+
+    let mut head = format!("HTTP/1.1 {} Mock\r\n", response.status);
+
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+
+    head.push_str(&format!(
+        "content-length: {}\r\nconnection: close\r\n\r\n",
+        response.body.len()
+    ));
+
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(response.body.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<RecordedRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0; 4096];
+
+    let head_end = loop {
+        if let Some(idx) = buf.windows(4).position(|x| x == b"\r\n\r\n") {
+            break idx;
+        }
+
+        let read = stream.read(&mut chunk).await.ok()?;
+
+        if read == 0 {
+            return None;
+        }
+
+        buf.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split(' ');
+
+    let method = request_line.next()?.to_owned();
+    let path = request_line.next()?.to_owned();
+
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_owned()))
+        .collect::<HashMap<String, String>>();
+
+    let length = headers
+        .get("content-length")
+        .and_then(|x| x.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let mut body = buf[head_end + 4..].to_vec();
+
+    while body.len() < length {
+        let read = stream.read(&mut chunk).await.ok()?;
+
+        if read == 0 {
+            break;
+        }
+
+        body.extend_from_slice(&chunk[..read]);
+    }
+
+    Some(RecordedRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use severn::consensus::{Consensus, Resolution};
+use severn::models::{MockModel, ModelSettings, Usage};
+use severn::pipeline::Pipeline;
+
+mod common;
+
+use common::agent;
+
+#[tokio::test]
+async fn majority_vote_picks_the_most_common_answer() {
+    let model = Arc::new(
+        MockModel::new()
+            .with_agent_response("One", "True")
+            .with_agent_response("Two", "false")
+            .with_agent_response("Three", " true "),
+    );
+
+    let res = Consensus::new()
+        .add_agent(agent("One"))
+        .add_agent(agent("Two"))
+        .add_agent(agent("Three"))
+        .run("Is the sky blue?".into(), model)
+        .await
+        .unwrap();
+
+    assert_eq!(res.output, "True");
+    assert_eq!(res.answers, vec!["True", "false", " true "]);
+}
+
+#[tokio::test]
+async fn every_agent_revises_each_round() {
+    let model = Arc::new(MockModel::new().with_default_response("yes"));
+
+    Consensus::new()
+        .add_agent(agent("One"))
+        .add_agent(agent("Two"))
+        .with_rounds(2)
+        .run("Is the sky blue?".into(), model.clone())
+        .await
+        .unwrap();
+
+    let calls = model.calls_for("One");
+
+    assert_eq!(calls.len(), 3);
+    assert!(calls[1].data.contains("Answer from Two"));
+    assert!(!calls[1].data.contains("Answer from One"));
+}
+
+#[tokio::test]
+async fn judge_picks_the_answer() {
+    let model = Arc::new(
+        MockModel::new()
+            .with_agent_response("One", "yes")
+            .with_agent_response("Two", "no")
+            .with_agent_response("Judge", "no"),
+    );
+
+    let res = Consensus::new()
+        .add_agent(agent("One"))
+        .add_agent(agent("Two"))
+        .with_resolution(Resolution::Judge(agent("Judge")))
+        .run("Is the sky green?".into(), model)
+        .await
+        .unwrap();
+
+    assert_eq!(res.output, "no");
+}
+
+#[tokio::test]
+async fn prompts_are_not_indented() {
+    let model = Arc::new(
+        MockModel::new()
+            .with_agent_response("One", "yes")
+            .with_agent_response("Two", "no")
+            .with_agent_response("Judge", "no"),
+    );
+
+    Consensus::new()
+        .add_agent(agent("One"))
+        .add_agent(agent("Two"))
+        .with_rounds(1)
+        .with_resolution(Resolution::Judge(agent("Judge")))
+        .run("Is the sky green?".into(), model.clone())
+        .await
+        .unwrap();
+
+    let revision = &model.calls_for("One")[1];
+    let judge = &model.calls_for("Judge")[0];
+
+    for text in [&revision.prompt, &revision.data, &judge.prompt] {
+        assert!(text.lines().all(|line| !line.starts_with(' ')), "{text}");
+    }
+
+    assert!(revision
+        .prompt
+        .starts_with("Is the sky green?\n\nYou have already answered"));
+    assert!(revision.data.starts_with("Your previous answer:\nyes\n\n"));
+}
+
+#[tokio::test]
+async fn usage_covers_every_round_and_the_judge() {
+    let model = Arc::new(
+        MockModel::new()
+            .with_default_response("yes")
+            .with_usage(Usage::new(10, 5)),
+    );
+
+    let res = Consensus::new()
+        .add_agent(agent("One"))
+        .add_agent(agent("Two"))
+        .with_rounds(1)
+        .with_resolution(Resolution::Judge(agent("Judge")))
+        .run("Is the sky blue?".into(), model)
+        .await
+        .unwrap();
+
+    assert_eq!(res.report.agent("One").unwrap().usage.total_tokens, 30);
+    assert_eq!(res.report.agent("Judge").unwrap().usage.total_tokens, 15);
+    assert_eq!(res.report.total().usage.total_tokens, 75);
+}
+
+#[tokio::test]
+async fn pipelines_run_consensus_with_their_models_and_settings() {
+    let model = Arc::new(MockModel::new().with_default_response("yes"));
+    let override_model = Arc::new(MockModel::new().with_default_response("no"));
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .with_agent_model("Two", override_model.clone())
+        .with_settings(ModelSettings::new().with_temperature(0.1));
+
+    let res = pipeline
+        .run_consensus(
+            &Consensus::new()
+                .add_agent(agent("One"))
+                .add_agent(agent("Two"))
+                .add_agent(agent("Three")),
+            "Is the sky blue?".into(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(res.output, "yes");
+    assert_eq!(override_model.calls_for("Two").len(), 1);
+    assert!(model.calls_for("Two").is_empty());
+    assert_eq!(model.calls_for("One")[0].settings.temperature, Some(0.1));
+}
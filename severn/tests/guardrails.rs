@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use severn::errors::Error;
+use severn::guardrails::{
+    DenyList, Guardrail, MaxLength, RedactPii, RequiredKeywords, ValidJson, Verdict,
+};
+use severn::models::{MockModel, Usage};
+use severn::pipeline::{Pipeline, PipelineEvent};
+use severn::structured::OutputSchema;
+
+mod common;
+
+use common::{agent, TestAgent};
+
+#[tokio::test]
+async fn built_in_guardrails() {
+    assert_eq!(MaxLength::new(5).check("hello").await, Verdict::Allow);
+    assert_eq!(
+        MaxLength::new(5)
+            .with_truncate(true)
+            .check("hello world")
+            .await,
+        Verdict::Rewrite("hello".into())
+    );
+    assert!(matches!(
+        MaxLength::new(5).check("hello world").await,
+        Verdict::Reask(_)
+    ));
+
+    let deny = DenyList::new(&[r"(?i)password"]).unwrap();
+
+    assert!(matches!(
+        deny.check("My PASSWORD is hunter2").await,
+        Verdict::Block(_)
+    ));
+    assert!(matches!(DenyList::new(&["("]), Err(Error::RegexError(_))));
+
+    assert_eq!(
+        RequiredKeywords::new(&["refund", "Order"])
+            .check("Your order has shipped")
+            .await,
+        Verdict::Reask("The answer has to mention: refund.".into())
+    );
+
+    assert_eq!(ValidJson::new().check(r#"{"a": 1}"#).await, Verdict::Allow);
+    assert!(matches!(
+        ValidJson::new().check("Sure! {\"a\": 1}").await,
+        Verdict::Reask(_)
+    ));
+
+    let schema = OutputSchema::new(
+        "Answer",
+        serde_json::json!({ "type": "object", "required": ["answer"] }),
+    );
+
+    assert!(matches!(
+        ValidJson::new()
+            .with_schema(schema)
+            .check(r#"{"a": 1}"#)
+            .await,
+        Verdict::Reask(_)
+    ));
+}
+
+#[tokio::test]
+async fn pii_is_redacted() {
+    let redact = RedactPii::new();
+
+    assert_eq!(
+        redact
+            .check("Email jo@example.com or call +44 20 7946 0958. Card: 4111 1111 1111 1111.")
+            .await,
+        Verdict::Rewrite("Email [EMAIL] or call [PHONE]. Card: [CREDIT_CARD].".into())
+    );
+    assert_eq!(redact.check("Order 1234 shipped").await, Verdict::Allow);
+}
+
+#[tokio::test]
+async fn input_guardrails_rewrite_what_the_model_sees() {
+    let model = Arc::new(MockModel::new().with_default_response("Noted"));
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .with_input_guardrail(Arc::new(RedactPii::new()))
+        .add_agent(agent("Support"));
+
+    pipeline
+        .run_pipeline("Reach me at jo@example.com".into())
+        .await
+        .unwrap();
+
+    assert_eq!(model.calls()[0].prompt, "Reach me at [EMAIL]");
+}
+
+#[tokio::test]
+async fn blocked_inputs_name_the_guardrail_and_agent() {
+    let model = Arc::new(MockModel::new().with_default_response("Noted"));
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .with_input_guardrail(Arc::new(DenyList::new(&["password"]).unwrap()))
+        .add_agent(agent("Support"));
+
+    let res = pipeline.run_pipeline("My password is hunter2".into()).await;
+
+    assert!(matches!(
+        res,
+        Err(Error::GuardrailViolation { guardrail, agent, .. })
+            if guardrail == "DenyList" && agent == "Support"
+    ));
+    assert!(model.calls().is_empty());
+}
+
+#[tokio::test]
+async fn failed_outputs_are_reasked() {
+    let model = Arc::new(
+        MockModel::new()
+            .with_queued_response("Sorry about that")
+            .with_queued_response("Sorry, we've issued a refund")
+            .with_usage(Usage::new(10, 5)),
+    );
+
+    let pipeline = Pipeline::new().with_model(model.clone()).add_agent(
+        TestAgent::new("Support")
+            .with_output_guardrail(Arc::new(RequiredKeywords::new(&["refund"])))
+            .build(),
+    );
+
+    let (res, report) = pipeline
+        .run_pipeline_with_usage("My order never came".into())
+        .await
+        .unwrap();
+
+    assert_eq!(res, "Sorry, we've issued a refund");
+    assert_eq!(report.total().usage, Usage::new(20, 10));
+
+    let calls = model.calls();
+
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[1].messages[2].text(), "Sorry about that");
+    assert!(calls[1]
+        .prompt
+        .contains("The answer has to mention: refund."));
+}
+
+#[tokio::test]
+async fn reasking_gives_up_after_the_limit() {
+    let model = Arc::new(MockModel::new().with_default_response("Sorry about that"));
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .with_output_guardrail(Arc::new(RequiredKeywords::new(&["refund"])))
+        .add_agent(agent("Support"));
+
+    let res = pipeline.run_pipeline("My order never came".into()).await;
+
+    assert!(matches!(
+        res,
+        Err(Error::GuardrailViolation { guardrail, agent, .. })
+            if guardrail == "RequiredKeywords" && agent == "Support"
+    ));
+    assert_eq!(model.calls().len(), 3);
+}
+
+#[tokio::test]
+async fn streamed_outputs_are_checked_before_they_are_sent() {
+    let model = Arc::new(MockModel::new().with_default_response("Call 555-123-4567"));
+
+    let pipeline = Pipeline::new().with_model(model).add_agent(
+        TestAgent::new("Support")
+            .with_output_guardrail(Arc::new(RedactPii::new()))
+            .build(),
+    );
+
+    let events = pipeline
+        .run_pipeline_stream("How do I reach you?".into(), false)
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(
+        events[0].as_ref().unwrap(),
+        &PipelineEvent::Delta("Call [PHONE]".into())
+    );
+}
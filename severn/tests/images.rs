@@ -0,0 +1,156 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use severn::data_sources::DataSource;
+use severn::errors::Error;
+use severn::models::{ChatRequest, Completion, Image, MockModel, ModelSettings, PromptModel};
+use severn::pipeline::Pipeline;
+
+mod common;
+
+use common::agent;
+
+struct ScannedForm;
+
+#[async_trait]
+impl DataSource for ScannedForm {
+    async fn retrieve_data(&self) -> Result<String, Error> {
+        Ok(String::from("Form 12B"))
+    }
+
+    async fn retrieve_images(&self) -> Result<Vec<Image>, Error> {
+        Ok(vec![Image::from_bytes(b"scan", "image/png")])
+    }
+}
+
+// A model that can't see images, so it records whether it was sent any
+#[derive(Default)]
+struct TextOnly {
+    sent_images: AtomicBool,
+}
+
+#[async_trait]
+impl PromptModel for TextOnly {
+    async fn chat(&self, request: &ChatRequest) -> Result<Completion, Error> {
+        if request.has_images() {
+            self.sent_images.store(true, Ordering::SeqCst);
+        }
+
+        Ok(Completion::new("text".into(), "text-only"))
+    }
+}
+
+#[test]
+fn images_round_trip_through_data_urls() {
+    let image = Image::from_bytes(b"hello", "image/png");
+
+    assert_eq!(image.data(), "aGVsbG8=");
+    assert_eq!(image.data_url(), "data:image/png;base64,aGVsbG8=");
+    assert_eq!(Image::from_data_url(&image.data_url()).unwrap(), image);
+
+    assert!(matches!(
+        Image::from_data_url("https://example.com/cat.png"),
+        Err(Error::InvalidImage(_))
+    ));
+}
+
+#[test]
+fn images_are_read_from_files() {
+    let dir = std::env::temp_dir().join(format!("severn-images-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    std::fs::write(dir.join("scan.JPG"), b"hello").unwrap();
+    std::fs::write(dir.join("notes.txt"), b"hello").unwrap();
+
+    let image = Image::from_path(dir.join("scan.JPG")).unwrap();
+
+    assert_eq!(image.media_type(), "image/jpeg");
+    assert_eq!(image.data(), "aGVsbG8=");
+
+    assert!(matches!(
+        Image::from_path(dir.join("notes.txt")),
+        Err(Error::InvalidImage(_))
+    ));
+    assert!(matches!(
+        Image::from_path(dir.join("missing.png")),
+        Err(Error::IoError(_))
+    ));
+}
+
+#[tokio::test]
+async fn every_agent_is_shown_the_images() {
+    let model = Arc::new(MockModel::new().with_default_response("looks fine"));
+    let screenshot = Image::from_bytes(b"screenshot", "image/png");
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .add_agent(agent("Reader"))
+        .add_agent(agent("Reviewer"));
+
+    pipeline
+        .run_pipeline_with_images("Review this".into(), vec![screenshot.clone()])
+        .await
+        .unwrap();
+
+    let calls = model.calls();
+
+    assert_eq!(calls.len(), 2);
+    assert!(calls
+        .iter()
+        .all(|call| call.images == vec![screenshot.clone()]));
+}
+
+#[tokio::test]
+async fn data_sources_can_supply_images() {
+    let model = Arc::new(MockModel::new().with_default_response("approved"));
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .add_agent(agent("Reviewer"));
+
+    pipeline
+        .run_pipeline_with_initial_data("Check the form".into(), ScannedForm)
+        .await
+        .unwrap();
+
+    let call = &model.calls()[0];
+
+    assert_eq!(call.data, "Form 12B");
+    assert_eq!(call.images, vec![Image::from_bytes(b"scan", "image/png")]);
+}
+
+#[tokio::test]
+async fn text_only_models_reject_images() {
+    let model = Arc::new(TextOnly::default());
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .add_agent(agent("Reviewer"));
+
+    let res = pipeline
+        .run_pipeline_with_images(
+            "Review this".into(),
+            vec![Image::from_bytes(b"screenshot", "image/png")],
+        )
+        .await;
+
+    assert!(matches!(res, Err(Error::ImagesNotSupported(agent)) if agent == "Reviewer"));
+
+    let res = model
+        .prompt_with_images(
+            "Review this",
+            &[Image::from_bytes(b"screenshot", "image/png")],
+            "None".into(),
+            &agent("Reviewer"),
+            &ModelSettings::new(),
+        )
+        .await;
+
+    assert!(matches!(res, Err(Error::ImagesNotSupported(agent)) if agent == "Reviewer"));
+    assert!(!model.sent_images.load(Ordering::SeqCst));
+
+    let res = pipeline.run_pipeline("Review this".into()).await;
+
+    assert_eq!(res.unwrap(), "text");
+}
@@ -0,0 +1,206 @@
+#![cfg(feature = "local-embeddings")]
+
+use std::path::PathBuf;
+
+use severn::models::{EmbedModel, LocalEmbedder, Pooling};
+
+const VOCAB: [&str; 8] = [
+    "[PAD]", "[UNK]", "hello", "world", "rivers", "are", "long", "short",
+];
+const HIDDEN: usize = 8;
+const INTERMEDIATE: usize = 16;
+const POSITIONS: usize = 16;
+
+// A tiny BERT model with made up weights, so the embedder can be tested without downloading one
+fn write_model(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("severn-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let config = serde_json::json!({
+        "vocab_size": VOCAB.len(),
+        "hidden_size": HIDDEN,
+        "num_hidden_layers": 1,
+        "num_attention_heads": 2,
+        "intermediate_size": INTERMEDIATE,
+        "hidden_act": "gelu",
+        "hidden_dropout_prob": 0.0,
+        "max_position_embeddings": POSITIONS,
+        "type_vocab_size": 2,
+        "initializer_range": 0.02,
+        "layer_norm_eps": 1e-12,
+        "pad_token_id": 0,
+        "classifier_dropout": null,
+        "model_type": "bert"
+    });
+    std::fs::write(dir.join("config.json"), config.to_string()).unwrap();
+
+    let vocab = VOCAB
+        .iter()
+        .enumerate()
+        .map(|(idx, word)| (word.to_string(), serde_json::json!(idx)))
+        .collect::<serde_json::Map<String, serde_json::Value>>();
+
+    let tokenizer = serde_json::json!({
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": null,
+        "pre_tokenizer": { "type": "Whitespace" },
+        "post_processor": null,
+        "decoder": null,
+        "model": { "type": "WordLevel", "vocab": vocab, "unk_token": "[UNK]" }
+    });
+    std::fs::write(dir.join("tokenizer.json"), tokenizer.to_string()).unwrap();
+
+    let layer = "encoder.layer.0";
+    let mut tensors = vec![
+        (
+            "embeddings.word_embeddings.weight".to_string(),
+            vec![VOCAB.len(), HIDDEN],
+        ),
+        (
+            "embeddings.position_embeddings.weight".to_string(),
+            vec![POSITIONS, HIDDEN],
+        ),
+        (
+            "embeddings.token_type_embeddings.weight".to_string(),
+            vec![2, HIDDEN],
+        ),
+    ];
+
+    for name in ["query", "key", "value"] {
+        tensors.push((
+            format!("{layer}.attention.self.{name}.weight"),
+            vec![HIDDEN, HIDDEN],
+        ));
+        tensors.push((format!("{layer}.attention.self.{name}.bias"), vec![HIDDEN]));
+    }
+
+    tensors.extend([
+        (
+            format!("{layer}.attention.output.dense.weight"),
+            vec![HIDDEN, HIDDEN],
+        ),
+        (format!("{layer}.attention.output.dense.bias"), vec![HIDDEN]),
+        (
+            format!("{layer}.intermediate.dense.weight"),
+            vec![INTERMEDIATE, HIDDEN],
+        ),
+        (
+            format!("{layer}.intermediate.dense.bias"),
+            vec![INTERMEDIATE],
+        ),
+        (
+            format!("{layer}.output.dense.weight"),
+            vec![HIDDEN, INTERMEDIATE],
+        ),
+        (format!("{layer}.output.dense.bias"), vec![HIDDEN]),
+    ]);
+
+    for prefix in [
+        "embeddings.LayerNorm".to_string(),
+        format!("{layer}.attention.output.LayerNorm"),
+        format!("{layer}.output.LayerNorm"),
+    ] {
+        tensors.push((format!("{prefix}.weight"), vec![HIDDEN]));
+        tensors.push((format!("{prefix}.bias"), vec![HIDDEN]));
+    }
+
+    std::fs::write(dir.join("model.safetensors"), safetensors(&tensors)).unwrap();
+
+    dir
+}
+
+// Writes the safetensors format by hand: the header length, a JSON header, then the raw data
+fn safetensors(tensors: &[(String, Vec<usize>)]) -> Vec<u8> {
+    let mut header = serde_json::Map::new();
+    let mut data = Vec::new();
+    let mut seed = 1u32;
+
+    for (name, shape) in tensors {
+        let len = shape.iter().product::<usize>();
+        let start = data.len();
+
+        for _ in 0..len {
+            //A small linear congruential generator keeps the weights the same on every run
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let value = (seed >> 8) as f32 / (1 << 24) as f32 - 0.5;
+
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        header.insert(
+            name.to_owned(),
+            serde_json::json!({
+                "dtype": "F32",
+                "shape": shape,
+                "data_offsets": [start, data.len()]
+            }),
+        );
+    }
+
+    let header = serde_json::Value::Object(header).to_string();
+
+    let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(&data);
+
+    bytes
+}
+
+fn assert_close(left: &[f32], right: &[f32]) {
+    assert_eq!(left.len(), right.len());
+
+    for (l, r) in left.iter().zip(right) {
+        assert!((l - r).abs() < 1e-5, "{left:?} != {right:?}");
+    }
+}
+
+#[tokio::test]
+async fn embeddings_are_normalized_and_deterministic() {
+    let embedder = LocalEmbedder::from_path(write_model("normalized")).unwrap();
+
+    let first = embedder.embed_sentence("hello world").await.unwrap();
+    let second = embedder.embed_sentence("hello world").await.unwrap();
+    let other = embedder.embed_sentence("rivers are long").await.unwrap();
+
+    assert_eq!(first.len(), HIDDEN);
+    assert_close(&first, &second);
+    assert_ne!(first, other);
+
+    let norm = first.iter().map(|x| x * x).sum::<f32>().sqrt();
+    assert!((norm - 1.0).abs() < 1e-4);
+}
+
+#[tokio::test]
+async fn padding_doesnt_change_mean_pooling() {
+    let embedder = LocalEmbedder::from_path(write_model("padding")).unwrap();
+
+    let alone = embedder.embed_sentence("hello").await.unwrap();
+    let batched = embedder
+        .embed_file(vec!["hello".into(), "rivers are long and short".into()])
+        .await
+        .unwrap();
+
+    assert_eq!(batched.len(), 2);
+    assert_close(&alone, &batched[0]);
+}
+
+#[tokio::test]
+async fn cls_pooling_without_normalizing() {
+    let embedder = LocalEmbedder::from_path(write_model("cls"))
+        .unwrap()
+        .with_pooling(Pooling::Cls)
+        .with_normalize(false);
+
+    let mean = LocalEmbedder::from_path(write_model("mean"))
+        .unwrap()
+        .with_normalize(false);
+
+    let cls = embedder.embed_sentence("rivers are long").await.unwrap();
+    let mean = mean.embed_sentence("rivers are long").await.unwrap();
+
+    assert_eq!(cls.len(), HIDDEN);
+    assert_ne!(cls, mean);
+}
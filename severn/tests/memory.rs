@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use severn::agents::traits::Agent;
+use severn::errors::Error;
+use severn::memory::{InMemoryStore, Memory};
+use severn::models::MockModel;
+use severn::pipeline::Pipeline;
+
+struct Support;
+
+impl Agent for Support {
+    fn name(&self) -> String {
+        "Support".into()
+    }
+
+    fn system_message(&self) -> String {
+        "You help customers".into()
+    }
+}
+
+fn memory(store: Arc<InMemoryStore>) -> Memory {
+    Memory::new(Arc::new(MockModel::new()), store)
+}
+
+#[tokio::test]
+async fn memories_are_recalled_by_scope_and_similarity() {
+    let store = Arc::new(InMemoryStore::new());
+    let memory = memory(store.clone()).with_top_k(2);
+
+    memory
+        .remember("alice", "Alice's router keeps dropping")
+        .await
+        .unwrap();
+    memory
+        .remember("alice", "Alice is on the premium plan")
+        .await
+        .unwrap();
+    memory
+        .remember("alice", "Alice prefers email")
+        .await
+        .unwrap();
+    memory
+        .remember("bob", "Bob's router keeps dropping")
+        .await
+        .unwrap();
+
+    assert_eq!(store.records().len(), 4);
+
+    let recalled = memory
+        .recall("alice", "Alice is on the premium plan")
+        .await
+        .unwrap();
+
+    assert_eq!(recalled.len(), 2);
+    assert_eq!(recalled[0].text, "Alice is on the premium plan");
+    assert!((recalled[0].score - 1.0).abs() < 1e-5);
+    assert!(recalled[0].score >= recalled[1].score);
+
+    let recalled = memory.recall("carol", "Anything").await.unwrap();
+
+    assert!(recalled.is_empty());
+}
+
+#[tokio::test]
+async fn pipelines_remember_earlier_sessions() {
+    let store = Arc::new(InMemoryStore::new());
+    let model = Arc::new(MockModel::new().with_default_response("Try restarting it"));
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .with_memory(memory(store.clone()))
+        .add_agent(Arc::new(Support));
+
+    pipeline
+        .run_pipeline_with_memory("My router keeps dropping".into(), "alice")
+        .await
+        .unwrap();
+
+    assert!(model.calls()[0].data.starts_with("None"));
+    assert_eq!(store.records()[0].scope, "alice");
+    assert_eq!(
+        store.records()[0].text,
+        "Prompt: My router keeps dropping\nAnswer: Try restarting it"
+    );
+
+    pipeline
+        .run_pipeline_with_memory("It's dropping again".into(), "alice")
+        .await
+        .unwrap();
+    pipeline
+        .run_pipeline_with_memory("Hello".into(), "bob")
+        .await
+        .unwrap();
+
+    let calls = model.calls();
+
+    assert!(calls[1].data.starts_with(
+        "Relevant memories:\n- Prompt: My router keeps dropping\nAnswer: Try restarting it\n\nNone"
+    ));
+    assert!(!calls[2].data.contains("Relevant memories"));
+}
+
+#[tokio::test]
+async fn memory_has_to_be_set() {
+    let pipeline = Pipeline::new()
+        .with_model(Arc::new(MockModel::new().with_default_response("Hi")))
+        .add_agent(Arc::new(Support));
+
+    let res = pipeline
+        .run_pipeline_with_memory("Hello".into(), "alice")
+        .await;
+
+    assert!(matches!(res, Err(Error::NoMemorySet)));
+}
@@ -0,0 +1,104 @@
+use severn::errors::Error;
+use severn::models::{EmbedModel, MockModel, ModelSettings, PromptModel};
+
+mod common;
+
+use common::agent;
+
+#[tokio::test]
+async fn agent_responses_take_priority() {
+    let model = MockModel::new()
+        .with_agent_response("Writer", "by agent")
+        .with_prompt_response("weather", "by prompt")
+        .with_default_response("default");
+
+    let res = model
+        .prompt("What's the weather?", "None".into(), &agent("Writer"))
+        .await
+        .unwrap();
+
+    assert_eq!(res, "by agent");
+}
+
+#[tokio::test]
+async fn prompt_responses_match_on_prompt_or_context() {
+    let model = MockModel::new()
+        .with_prompt_response("weather", "sunny")
+        .with_prompt_response("Paris", "France");
+
+    let by_prompt = model
+        .prompt("What's the weather?", "None".into(), &agent("A"))
+        .await
+        .unwrap();
+    let by_context = model
+        .prompt("Where is this?", "Paris".into(), &agent("A"))
+        .await
+        .unwrap();
+
+    assert_eq!(by_prompt, "sunny");
+    assert_eq!(by_context, "France");
+}
+
+#[tokio::test]
+async fn queued_responses_are_used_once_in_order() {
+    let model = MockModel::new()
+        .with_queued_response("first")
+        .with_queued_response("second");
+
+    let agent = agent("A");
+
+    assert_eq!(model.prompt("", "".into(), &agent).await.unwrap(), "first");
+    assert_eq!(model.prompt("", "".into(), &agent).await.unwrap(), "second");
+
+    let res = model.prompt("", "".into(), &agent).await;
+
+    assert!(matches!(res, Err(Error::NoMockResponse(name)) if name == "A"));
+}
+
+#[tokio::test]
+async fn records_every_call() {
+    let model = MockModel::new().with_default_response("ok");
+    let settings = ModelSettings::new().with_temperature(0.2);
+
+    model
+        .prompt_with_settings("first", "data".into(), &agent("A"), &settings)
+        .await
+        .unwrap();
+    model
+        .prompt("second", "None".into(), &agent("B"))
+        .await
+        .unwrap();
+
+    let calls = model.calls();
+
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0].agent, "A");
+    assert_eq!(calls[0].prompt, "first");
+    assert_eq!(calls[0].data, "data");
+    assert_eq!(calls[0].settings, settings);
+    assert_eq!(model.calls_for("B")[0].prompt, "second");
+}
+
+#[tokio::test]
+async fn embeddings_are_deterministic() {
+    let model = MockModel::new().with_dimensions(16);
+
+    let first = model.embed_sentence("hello world").await.unwrap();
+    let second = model.embed_sentence("hello world").await.unwrap();
+    let other = model.embed_sentence("goodbye world").await.unwrap();
+
+    assert_eq!(first.len(), 16);
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+
+    let norm = first.iter().map(|x| x * x).sum::<f32>().sqrt();
+    assert!((norm - 1.0).abs() < 1e-4);
+
+    let chunks = model
+        .embed_file(vec!["hello world".into(), "goodbye world".into()])
+        .await
+        .unwrap();
+
+    assert_eq!(chunks, vec![first, other]);
+    assert_eq!(model.embedded().len(), 5);
+}
@@ -0,0 +1,259 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+use severn::errors::Error;
+use severn::models::{
+    ChatRequest, EmbedModel, Message, ModelSettings, Ollama, PromptModel, Role, StreamEvent,
+    ToolCall, Usage,
+};
+use severn::structured::OutputSchema;
+
+mod common;
+
+use common::server::{MockResponse, MockServer};
+use common::Weather;
+
+fn model(server: &MockServer) -> Ollama {
+    Ollama::new("llama3").with_base_url(server.url())
+}
+
+fn reply(message: Value) -> MockResponse {
+    MockResponse::json(json!({
+        "model": "llama3",
+        "message": message,
+        "done": true,
+        "prompt_eval_count": 10,
+        "eval_count": 5
+    }))
+}
+
+#[tokio::test]
+async fn chat_sends_the_messages_and_options() {
+    let server = MockServer::start().await;
+    server.respond(reply(
+        json!({"role": "assistant", "content": "{\"answer\": 4}"}),
+    ));
+
+    let schema = OutputSchema::new(
+        "Answer",
+        json!({"type": "object", "properties": {"answer": {"type": "integer"}}}),
+    );
+
+    let request = ChatRequest::new()
+        .with_system_message("You are Maths")
+        .with_message(Message::user("What's 2 + 2?"))
+        .with_settings(
+            ModelSettings::new()
+                .with_model("qwen2")
+                .with_temperature(0.0)
+                .with_max_tokens(20),
+        )
+        .with_output_schema(Some(schema));
+
+    let res = model(&server).chat(&request).await.unwrap();
+
+    assert_eq!(res.content, "{\"answer\": 4}");
+    assert_eq!(res.model, "qwen2");
+    assert_eq!(res.usage, Some(Usage::new(10, 5)));
+
+    let sent = &server.requests()[0];
+    let body = sent.json();
+
+    assert_eq!(sent.path, "/api/chat");
+    assert_eq!(body["model"], "qwen2");
+    assert_eq!(body["stream"], false);
+    assert_eq!(
+        body["options"],
+        json!({"temperature": 0.0, "num_predict": 20})
+    );
+    assert_eq!(body["format"]["properties"]["answer"]["type"], "integer");
+    assert_eq!(
+        body["messages"],
+        json!([
+            {"role": "system", "content": "You are Maths"},
+            {"role": "user", "content": "What's 2 + 2?"}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn tool_results_name_the_tool() {
+    let server = MockServer::start().await;
+    server
+        .respond(reply(json!({
+            "role": "assistant",
+            "content": "",
+            "tool_calls": [{"function": {"name": "get_weather", "arguments": {"city": "Bristol"}}}]
+        })))
+        .respond(reply(
+            json!({"role": "assistant", "content": "It's sunny."}),
+        ));
+
+    let request = ChatRequest::new()
+        .with_message(Message::user("What's the weather in Bristol?"))
+        .with_tools(vec![Arc::new(Weather)]);
+
+    let res = model(&server).chat(&request).await.unwrap();
+
+    assert_eq!(res.content, "It's sunny.");
+    assert_eq!(res.usage, Some(Usage::new(20, 10)));
+
+    let requests = server.requests();
+    let first = requests[0].json();
+    let second = requests[1].json();
+
+    assert_eq!(first["tools"][0]["type"], "function");
+    assert_eq!(first["tools"][0]["function"]["name"], "get_weather");
+
+    assert_eq!(
+        second["messages"],
+        json!([
+            {"role": "user", "content": "What's the weather in Bristol?"},
+            {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{"function": {"name": "get_weather", "arguments": {"city": "Bristol"}}}]
+            },
+            {"role": "tool", "content": "It's sunny in Bristol", "tool_name": "get_weather"}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn earlier_tool_results_are_named_from_their_call() {
+    let server = MockServer::start().await;
+    server.respond(reply(
+        json!({"role": "assistant", "content": "Still sunny."}),
+    ));
+
+    let request = ChatRequest::new()
+        .with_message(Message::user("What's the weather in Bristol?"))
+        .with_message(
+            Message::new(Role::Assistant).with_tool_calls(vec![ToolCall {
+                id: "call_1".into(),
+                name: "get_weather".into(),
+                arguments: json!({"city": "Bristol"}),
+            }]),
+        )
+        .with_message(Message::tool_result("call_1", "It's sunny in Bristol"))
+        .with_message(Message::user("And now?"));
+
+    model(&server).chat(&request).await.unwrap();
+
+    let body = server.requests()[0].json();
+
+    assert_eq!(body["messages"][2]["role"], "tool");
+    assert_eq!(body["messages"][2]["tool_name"], "get_weather");
+}
+
+#[tokio::test]
+async fn unknown_tools_are_reported_back_to_the_model() {
+    let server = MockServer::start().await;
+    server
+        .respond(reply(json!({
+            "role": "assistant",
+            "content": "",
+            "tool_calls": [{"function": {"name": "get_tides", "arguments": {}}}]
+        })))
+        .respond(reply(
+            json!({"role": "assistant", "content": "I can't check the tides."}),
+        ));
+
+    let request = ChatRequest::new()
+        .with_message(Message::user("When's high tide?"))
+        .with_tools(vec![Arc::new(Weather)]);
+
+    let res = model(&server).chat(&request).await.unwrap();
+
+    assert_eq!(res.content, "I can't check the tides.");
+
+    let body = server.requests()[1].json();
+
+    assert_eq!(
+        body["messages"][2]["content"],
+        "Error: there is no tool called get_tides"
+    );
+}
+
+#[tokio::test]
+async fn embeddings_are_batched_into_one_request() {
+    let server = MockServer::start().await;
+    server.respond(MockResponse::json(json!({
+        "model": "nomic-embed-text",
+        "embeddings": [[0.1, 0.2], [0.3, 0.4]],
+        "prompt_eval_count": 6
+    })));
+
+    let res = model(&server)
+        .embed_file_with_usage(vec!["first".into(), "second".into()])
+        .await
+        .unwrap();
+
+    assert_eq!(res.embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+    assert_eq!(res.model, "nomic-embed-text");
+    assert_eq!(res.usage, Some(Usage::new(6, 0)));
+
+    let sent = &server.requests()[0];
+
+    assert_eq!(sent.path, "/api/embed");
+    assert_eq!(
+        sent.json(),
+        json!({"model": "nomic-embed-text", "input": ["first", "second"]})
+    );
+}
+
+#[tokio::test]
+async fn streams_newline_delimited_json() {
+    let chunks = [
+        json!({"model": "llama3", "message": {"role": "assistant", "content": "Hel"}, "done": false}),
+        json!({"model": "llama3", "message": {"role": "assistant", "content": ""}, "done": false}),
+        json!({"model": "llama3", "message": {"role": "assistant", "content": "lo"}, "done": false}),
+        json!({"model": "llama3", "message": {"role": "assistant", "content": ""}, "done": true, "prompt_eval_count": 8, "eval_count": 2}),
+    ]
+    .iter()
+    .map(|chunk| format!("{chunk}\n"))
+    .collect::<String>();
+
+    let server = MockServer::start().await;
+    server.respond(MockResponse::text("application/x-ndjson", &chunks));
+
+    let events = model(&server)
+        .chat_stream(&ChatRequest::new().with_message(Message::user("Hi")))
+        .await
+        .unwrap()
+        .map(|event| event.unwrap())
+        .collect::<Vec<StreamEvent>>()
+        .await;
+
+    assert_eq!(
+        events,
+        vec![
+            StreamEvent::Delta("Hel".into()),
+            StreamEvent::Delta("lo".into()),
+            StreamEvent::Done {
+                model: "llama3".into(),
+                usage: Some(Usage::new(8, 2)),
+            },
+        ]
+    );
+
+    assert_eq!(server.requests()[0].json()["stream"], true);
+}
+
+#[tokio::test]
+async fn errors_carry_the_server_message() {
+    let server = MockServer::start().await;
+    server.respond(
+        MockResponse::json(json!({"error": "model \"llama3\" not found"})).with_status(404),
+    );
+
+    let res = model(&server)
+        .chat(&ChatRequest::new().with_message(Message::user("Hi")))
+        .await;
+
+    assert!(matches!(
+        res,
+        Err(Error::ProviderError { status: 404, message }) if message == "model \"llama3\" not found"
+    ));
+}
@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+use severn::errors::Error;
+use severn::models::{
+    ChatRequest, EmbedModel, Message, ModelSettings, OpenAI, OpenAICompatibleConfig, PromptModel,
+    StreamEvent, Usage,
+};
+
+mod common;
+
+use common::server::{MockResponse, MockServer};
+use common::{openai_reply, Weather};
+
+fn request() -> ChatRequest {
+    ChatRequest::new()
+        .with_system_message("You are Writer")
+        .with_message(Message::user("Write about rivers"))
+}
+
+#[tokio::test]
+async fn compatible_servers_get_the_base_url_and_model() {
+    let server = MockServer::start().await;
+    server.respond(openai_reply(
+        json!({"role": "assistant", "content": "Rivers flow."}),
+    ));
+
+    let model = OpenAI::compatible(&format!("{}/v1/", server.url())).with_model("llama-3.1-8b");
+
+    let res = model.chat(&request()).await.unwrap();
+
+    assert_eq!(res.content, "Rivers flow.");
+    assert_eq!(res.model, "llama-3.1-8b");
+    assert_eq!(res.usage, Some(Usage::new(10, 5)));
+
+    let sent = &server.requests()[0];
+
+    assert_eq!(sent.method, "POST");
+    assert_eq!(sent.path, "/v1/chat/completions");
+    assert_eq!(sent.json()["model"], "llama-3.1-8b");
+    assert_eq!(
+        sent.json()["messages"],
+        json!([
+            {"role": "system", "content": "You are Writer"},
+            {"role": "user", "content": "Write about rivers"}
+        ])
+    );
+
+    //Nothing is sent that the server didn't ask for
+    assert_eq!(sent.header("authorization"), None);
+    assert_eq!(sent.header("openai-beta"), None);
+    assert_eq!(sent.header("openai-organization"), None);
+}
+
+#[tokio::test]
+async fn settings_can_override_the_model() {
+    let server = MockServer::start().await;
+    server.respond(openai_reply(
+        json!({"role": "assistant", "content": "Rivers flow."}),
+    ));
+
+    let model = OpenAI::compatible(server.url()).with_model("llama-3.1-8b");
+    let request = request().with_settings(ModelSettings::new().with_model("qwen2.5-72b"));
+
+    let res = model.chat(&request).await.unwrap();
+
+    assert_eq!(res.model, "qwen2.5-72b");
+    assert_eq!(server.requests()[0].json()["model"], "qwen2.5-72b");
+}
+
+#[tokio::test]
+async fn configs_add_a_path_prefix_query_params_and_headers() {
+    let server = MockServer::start().await;
+    server.respond(openai_reply(
+        json!({"role": "assistant", "content": "Rivers flow."}),
+    ));
+
+    let config = OpenAICompatibleConfig::new(server.url())
+        .with_path_prefix("/openai/deployments/writer/")
+        .add_query_param("api-version", "2024-06-01")
+        .add_header("x-team", "docs".into())
+        .unwrap()
+        .with_api_key("secret");
+
+    OpenAI::from_config(config).chat(&request()).await.unwrap();
+
+    let sent = &server.requests()[0];
+
+    assert_eq!(
+        sent.path,
+        "/openai/deployments/writer/chat/completions?api-version=2024-06-01"
+    );
+    assert_eq!(sent.header("authorization"), Some("Bearer secret"));
+    assert_eq!(sent.header("x-team"), Some("docs"));
+    assert_eq!(sent.header("openai-beta"), None);
+}
+
+#[test]
+fn invalid_headers_are_errors() {
+    let res = OpenAICompatibleConfig::new("http://localhost:8000/v1")
+        .add_header("x-team", "docs\nand more".into());
+
+    assert!(matches!(res, Err(Error::InvalidHeaderValue(_))));
+}
+
+#[tokio::test]
+async fn embeddings_use_the_embedding_model() {
+    let server = MockServer::start().await;
+    server.respond(MockResponse::json(json!({
+        "object": "list",
+        "model": "bge-small",
+        "data": [
+            {"object": "embedding", "index": 1, "embedding": [0.3, 0.4]},
+            {"object": "embedding", "index": 0, "embedding": [0.1, 0.2]}
+        ],
+        "usage": {"prompt_tokens": 4, "total_tokens": 4}
+    })));
+
+    let model =
+        OpenAI::compatible(&format!("{}/v1", server.url())).with_embedding_model("bge-small");
+
+    let res = model
+        .embed_file_with_usage(vec!["first".into(), "second".into()])
+        .await
+        .unwrap();
+
+    //The embeddings are put back in the order of the inputs
+    assert_eq!(res.embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+    assert_eq!(res.usage, Some(Usage::new(4, 0)));
+
+    let sent = &server.requests()[0];
+
+    assert_eq!(sent.path, "/v1/embeddings");
+    assert_eq!(sent.json()["model"], "bge-small");
+    assert_eq!(sent.json()["input"], json!(["first", "second"]));
+}
+
+fn chunk(delta: Value, usage: Option<Value>) -> String {
+    let choices = match usage {
+        Some(_) => json!([]),
+        None => json!([{"index": 0, "delta": delta, "finish_reason": null}]),
+    };
+
+    let chunk = json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion.chunk",
+        "created": 0,
+        "model": "gpt-4o",
+        "choices": choices,
+        "usage": usage
+    });
+
+    format!("data: {chunk}")
+}
+
+async fn collect(model: &impl PromptModel, request: &ChatRequest) -> Vec<StreamEvent> {
+    model
+        .chat_stream(request)
+        .await
+        .unwrap()
+        .map(|event| event.unwrap())
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn streams_deltas_then_usage() {
+    let server = MockServer::start().await;
+    server.respond(MockResponse::sse(&[
+        chunk(json!({"role": "assistant", "content": ""}), None),
+        chunk(json!({"content": "Rivers "}), None),
+        chunk(json!({"content": "flow."}), None),
+        chunk(
+            json!({}),
+            Some(json!({"prompt_tokens": 10, "completion_tokens": 2, "total_tokens": 12})),
+        ),
+        "data: [DONE]".into(),
+    ]));
+
+    let events = collect(&OpenAI::compatible(server.url()), &request()).await;
+
+    assert_eq!(
+        events,
+        vec![
+            StreamEvent::Delta("Rivers ".into()),
+            StreamEvent::Delta("flow.".into()),
+            StreamEvent::Done {
+                model: "gpt-4o".into(),
+                usage: Some(Usage::new(10, 2)),
+            },
+        ]
+    );
+
+    let body = server.requests()[0].json();
+
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["stream_options"], json!({"include_usage": true}));
+}
+
+#[tokio::test]
+async fn streams_without_a_usage_chunk_still_finish() {
+    let server = MockServer::start().await;
+    server.respond(MockResponse::sse(&[
+        chunk(json!({"content": "Rivers flow."}), None),
+        "data: [DONE]".into(),
+    ]));
+
+    let events = collect(&OpenAI::compatible(server.url()), &request()).await;
+
+    assert_eq!(
+        events,
+        vec![
+            StreamEvent::Delta("Rivers flow.".into()),
+            StreamEvent::Done {
+                model: "gpt-4o".into(),
+                usage: None,
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn requests_with_tools_are_not_streamed() {
+    let server = MockServer::start().await;
+    server.respond(openai_reply(
+        json!({"role": "assistant", "content": "It's sunny."}),
+    ));
+
+    let request = request().with_tools(vec![Arc::new(Weather)]);
+    let events = collect(&OpenAI::compatible(server.url()), &request).await;
+
+    assert_eq!(
+        events,
+        vec![
+            StreamEvent::Delta("It's sunny.".into()),
+            StreamEvent::Done {
+                model: "gpt-4o".into(),
+                usage: Some(Usage::new(10, 5)),
+            },
+        ]
+    );
+    assert_eq!(server.requests()[0].json()["stream"], Value::Null);
+}
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use severn::blackboard::Blackboard;
+use severn::errors::Error;
+use severn::models::{MockModel, ModelSettings, PriceTable, UsageReport};
+use severn::pipeline::{Pipeline, PipelineEvent};
+
+mod common;
+
+use common::{agent, TestAgent};
+
+#[tokio::test]
+async fn each_agent_gets_the_previous_output() {
+    let model = Arc::new(
+        MockModel::new()
+            .with_agent_response("Researcher", "research notes")
+            .with_agent_response("Writer", "the article"),
+    );
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .add_agent(agent("Researcher"))
+        .add_agent(agent("Writer"));
+
+    let res = pipeline.run_pipeline("Write about rivers".into()).await;
+
+    assert_eq!(res.unwrap(), "the article");
+
+    let calls = model.calls();
+
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0].data, "None");
+    assert_eq!(calls[1].data, "research notes");
+    assert!(calls.iter().all(|call| call.prompt == "Write about rivers"));
+}
+
+#[tokio::test]
+async fn empty_pipeline_errors() {
+    let pipeline = Pipeline::new().with_model(Arc::new(MockModel::new()));
+
+    let res = pipeline.run_pipeline("Hello".into()).await;
+
+    assert!(matches!(res, Err(Error::NoAgentsExist)));
+}
+
+#[tokio::test]
+async fn missing_model_errors() {
+    let pipeline = Pipeline::new().add_agent(agent("Writer"));
+
+    let res = pipeline.run_pipeline("Hello".into()).await;
+
+    assert!(matches!(res, Err(Error::NoModelSet(name)) if name == "Writer"));
+}
+
+#[tokio::test]
+async fn agent_models_override_the_default() {
+    let default_model = Arc::new(MockModel::new().with_default_response("default"));
+    let writer_model = Arc::new(MockModel::new().with_default_response("writer"));
+
+    let pipeline = Pipeline::new()
+        .with_model(default_model.clone())
+        .add_agent(agent("Researcher"))
+        .add_agent_with_model(agent("Writer"), writer_model.clone());
+
+    let res = pipeline.run_pipeline("Hello".into()).await.unwrap();
+
+    assert_eq!(res, "writer");
+    assert_eq!(default_model.calls_for("Researcher").len(), 1);
+    assert_eq!(writer_model.calls_for("Writer").len(), 1);
+}
+
+#[tokio::test]
+async fn pipeline_settings_are_passed_to_the_model() {
+    let model = Arc::new(MockModel::new().with_default_response("ok"));
+    let settings = ModelSettings::new().with_temperature(0.3).with_seed(7);
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .with_settings(settings.to_owned())
+        .add_agent(agent("Writer"));
+
+    pipeline.run_pipeline("Hello".into()).await.unwrap();
+
+    assert_eq!(model.calls()[0].settings, settings);
+}
+
+#[tokio::test]
+async fn agents_read_from_the_blackboard() {
+    let model = Arc::new(
+        MockModel::new()
+            .with_agent_response("Researcher", "research notes")
+            .with_agent_response("Outliner", "an outline")
+            .with_agent_response("Writer", "the article"),
+    );
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .add_agent(agent("Researcher"))
+        .add_agent(agent("Outliner"))
+        .add_agent(
+            TestAgent::new("Writer")
+                .with_reads(&["topic", "Researcher"])
+                .build(),
+        );
+
+    let blackboard = Blackboard::new().with_value("topic", "rivers").unwrap();
+
+    let blackboard = pipeline
+        .run_pipeline_with_blackboard("Hello".into(), blackboard)
+        .await
+        .unwrap();
+
+    let writer_context = &model.calls_for("Writer")[0].data;
+
+    assert!(writer_context.contains("rivers"));
+    assert!(writer_context.contains("research notes"));
+    assert!(!writer_context.contains("an outline"));
+    assert_eq!(blackboard.get_text("Writer").unwrap(), "the article");
+}
+
+#[tokio::test]
+async fn streaming_emits_steps_and_deltas() {
+    let model = Arc::new(
+        MockModel::new()
+            .with_agent_response("Researcher", "research notes")
+            .with_agent_response("Writer", "the article"),
+    );
+
+    let pipeline = Pipeline::new()
+        .with_model(model)
+        .add_agent(agent("Researcher"))
+        .add_agent(agent("Writer"));
+
+    let mut expected_report = UsageReport::new();
+    expected_report.record("Researcher", "mock", None, &PriceTable::new());
+    expected_report.record("Writer", "mock", None, &PriceTable::new());
+
+    let events = pipeline
+        .run_pipeline_stream("Hello".into(), true)
+        .map(Result::unwrap)
+        .collect::<Vec<PipelineEvent>>()
+        .await;
+
+    assert_eq!(
+        events,
+        vec![
+            PipelineEvent::StepStarted {
+                agent: "Researcher".into()
+            },
+            PipelineEvent::StepFinished {
+                agent: "Researcher".into(),
+                output: "research notes".into()
+            },
+            PipelineEvent::StepStarted {
+                agent: "Writer".into()
+            },
+            PipelineEvent::Delta("the article".into()),
+            PipelineEvent::StepFinished {
+                agent: "Writer".into(),
+                output: "the article".into()
+            },
+            PipelineEvent::Done {
+                output: "the article".into(),
+                usage: None,
+                report: expected_report
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn streaming_reports_errors() {
+    let pipeline = Pipeline::new()
+        .with_model(Arc::new(MockModel::new()))
+        .add_agent(agent("Writer"));
+
+    let events = pipeline
+        .run_pipeline_stream("Hello".into(), false)
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(events.len(), 1);
+    assert!(matches!(events[0], Err(Error::NoMockResponse(_))));
+}
@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use severn::errors::Error;
+use severn::models::{MockModel, Usage};
+use severn::pipeline::Pipeline;
+use severn::planning::Plan;
+use severn::structured::OutputSchema;
+
+mod common;
+
+use common::{agent, TestAgent};
+
+fn pipeline(planner: Arc<MockModel>, model: Arc<MockModel>) -> Pipeline {
+    Pipeline::new()
+        .with_model(model)
+        .with_agent_model("Planner", planner)
+        .add_agent(
+            TestAgent::new("Researcher")
+                .with_description("Finds facts")
+                .build(),
+        )
+        .add_agent(
+            TestAgent::new("Writer")
+                .with_description("Writes articles")
+                .build(),
+        )
+        .add_agent(agent("Flaky"))
+}
+
+#[tokio::test]
+async fn steps_run_in_plan_order_and_see_earlier_results() {
+    let planner = Arc::new(MockModel::new().with_queued_response(
+        r#"{"steps": [
+            {"agent": "Researcher", "task": "Find facts about rivers"},
+            {"agent": "Writer", "task": "Write the article"}
+        ]}"#,
+    ));
+    let model = Arc::new(
+        MockModel::new()
+            .with_agent_response("Researcher", "rivers are long")
+            .with_agent_response("Writer", "the article")
+            .with_usage(Usage::new(10, 5)),
+    );
+
+    let run = pipeline(planner.clone(), model.clone())
+        .run_pipeline_with_plan("An article about rivers".into())
+        .await
+        .unwrap();
+
+    assert_eq!(run.output, "the article");
+    assert_eq!(run.steps.len(), 2);
+    assert_eq!(run.replans, 0);
+    assert!(run.report.agent("Writer").is_some());
+
+    let plan_call = &planner.calls()[0];
+
+    assert_eq!(plan_call.agent, "Planner");
+    assert_eq!(plan_call.prompt, "An article about rivers");
+    assert!(plan_call.messages[0]
+        .text()
+        .contains("- Researcher: Finds facts\n- Writer: Writes articles\n- Flaky\n"));
+
+    let calls = model.calls();
+
+    assert_eq!(calls[0].prompt, "Find facts about rivers");
+    assert_eq!(calls[1].prompt, "Write the article");
+    assert!(calls[1]
+        .data
+        .contains("The overall goal is: An article about rivers"));
+    assert!(calls[1]
+        .data
+        .contains("Step 1 (Researcher): Find facts about rivers\nrivers are long"));
+}
+
+#[tokio::test]
+async fn failed_steps_are_replanned() {
+    let planner = Arc::new(
+        MockModel::new()
+            .with_queued_response(
+                r#"{"steps": [
+                    {"agent": "Researcher", "task": "Find facts"},
+                    {"agent": "Checker", "task": "Check the facts"},
+                    {"agent": "Writer", "task": "Write the article"}
+                ]}"#,
+            )
+            .with_queued_response(
+                r#"{"steps": [
+                    {"agent": "Writer", "task": " "},
+                    {"agent": "Writer", "task": "Write the article"}
+                ]}"#,
+            )
+            .with_queued_response(
+                r#"{"steps": [{"agent": "Writer", "task": "Write it without checking"}]}"#,
+            ),
+    );
+    let model = Arc::new(
+        MockModel::new()
+            .with_agent_response("Researcher", "rivers are long")
+            .with_agent_response("Writer", "the article"),
+    );
+
+    let run = pipeline(planner.clone(), model.clone())
+        .run_pipeline_with_plan("An article about rivers".into())
+        .await
+        .unwrap();
+
+    assert_eq!(run.output, "the article");
+    assert_eq!(run.replans, 2);
+    assert_eq!(
+        run.steps
+            .iter()
+            .map(|step| step.task.as_str())
+            .collect::<Vec<&str>>(),
+        vec!["Find facts", "Write it without checking"]
+    );
+
+    let replans = planner.calls();
+
+    assert!(replans[1].data.contains("Step 1 (Researcher): Find facts"));
+    assert!(replans[1]
+        .data
+        .contains("The last step failed: Checker was asked to \"Check the facts\""));
+    assert!(replans[2]
+        .data
+        .contains("The planner returned an invalid step: Writer was given no task"));
+}
+
+#[tokio::test]
+async fn other_step_errors_are_not_replanned() {
+    let planner = Arc::new(
+        MockModel::new()
+            .with_default_response(r#"{"steps": [{"agent": "Flaky", "task": "Check the facts"}]}"#),
+    );
+
+    let res = pipeline(planner.clone(), Arc::new(MockModel::new()))
+        .run_pipeline_with_plan("An article about rivers".into())
+        .await;
+
+    assert!(matches!(res, Err(Error::NoMockResponse(name)) if name == "Flaky"));
+    assert_eq!(planner.calls().len(), 1);
+}
+
+#[tokio::test]
+async fn replanning_stops_at_the_limit() {
+    let planner =
+        Arc::new(MockModel::new().with_default_response(
+            r#"{"steps": [{"agent": "Editor", "task": "Edit the article"}]}"#,
+        ));
+    let model = Arc::new(MockModel::new());
+
+    let res = pipeline(planner.clone(), model)
+        .with_max_replans(1)
+        .run_pipeline_with_plan("An article about rivers".into())
+        .await;
+
+    assert!(matches!(res, Err(Error::NoAgentNamed(name)) if name == "Editor"));
+    assert_eq!(planner.calls().len(), 2);
+}
+
+#[tokio::test]
+async fn empty_plans_are_rejected() {
+    let planner = Arc::new(MockModel::new().with_default_response(r#"{"steps": []}"#));
+
+    let res = pipeline(planner, Arc::new(MockModel::new()))
+        .run_pipeline_with_plan("Nothing".into())
+        .await;
+
+    assert!(matches!(res, Err(Error::EmptyPlan)));
+}
+
+#[tokio::test]
+async fn unusable_plans_are_replanned() {
+    let planner = Arc::new(
+        MockModel::new()
+            .with_queued_response("1. Research rivers\n2. Write the article")
+            .with_queued_response(r#"{"steps": [{"agent": "Writer", "task": "Write it"}]}"#),
+    );
+    let model = Arc::new(MockModel::new().with_agent_response("Writer", "the article"));
+
+    let run = pipeline(planner.clone(), model)
+        .run_pipeline_with_plan("An article about rivers".into())
+        .await
+        .unwrap();
+
+    assert_eq!(run.output, "the article");
+    assert_eq!(run.replans, 1);
+    assert!(planner.calls()[1]
+        .data
+        .contains("Your last plan couldn't be used: Output did not match the expected schema"));
+
+    let planner = Arc::new(MockModel::new().with_default_response("Not a plan"));
+
+    let res = pipeline(planner.clone(), Arc::new(MockModel::new()))
+        .with_max_replans(1)
+        .run_pipeline_with_plan("An article about rivers".into())
+        .await;
+
+    assert!(matches!(res, Err(Error::InvalidStructuredOutput(_))));
+    assert_eq!(planner.calls().len(), 2);
+}
+
+#[test]
+fn planner_schema_matches_plans() {
+    let pipeline = Pipeline::new();
+    let planner = pipeline.planner();
+
+    assert_eq!(planner.name(), "Planner");
+    assert!(!planner.system_message().contains("numbered list"));
+
+    let schema = planner.output_schema().unwrap();
+
+    assert_eq!(schema, OutputSchema::of::<Plan>());
+
+    assert!(schema
+        .validate(r#"{"steps": [{"agent": "Writer", "task": "Write"}]}"#)
+        .is_ok());
+    assert!(schema.validate(r#"{"tasks": []}"#).is_err());
+}
@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use severn::agents::premade::{
+    ArticleWriter, Classification, Classifier, CodeReviewer, Critic, Editor, Extractor, Planner,
+    Researcher, Summarizer, SummaryFormat, SummaryLength, Translator,
+};
+use severn::agents::traits::Agent;
+use severn::models::MockModel;
+use severn::pipeline::Pipeline;
+use severn::planning::Plan;
+use severn::structured::OutputSchema;
+
+#[test]
+fn article_writer_and_researcher() {
+    let writer = ArticleWriter::new()
+        .with_target_audience("data engineers")
+        .with_tone("friendly");
+
+    assert!(writer
+        .system_message()
+        .contains("Your target audience is data engineers."));
+    assert!(writer
+        .system_message()
+        .contains("your tone should be: friendly."));
+    assert!(writer.description().contains("data engineers"));
+
+    assert!(Researcher::new()
+        .system_message()
+        .contains("research whatever query"));
+}
+
+#[test]
+fn summarizer_length_format_and_focus() {
+    let default = Summarizer::new().system_message();
+
+    assert!(default.contains("summarise the provided context in a single paragraph."));
+    assert!(default.contains("Write the summary as prose."));
+    assert!(!default.contains("Concentrate on"));
+
+    let summary = Summarizer::new()
+        .with_length(SummaryLength::Words(50))
+        .with_format(SummaryFormat::BulletPoints)
+        .with_focus("the financial figures")
+        .system_message();
+
+    assert!(summary.contains("in at most 50 words."));
+    assert!(summary.contains("list of bullet points"));
+    assert!(summary.contains("Concentrate on the financial figures."));
+
+    assert!(Summarizer::new()
+        .with_length(SummaryLength::Short)
+        .system_message()
+        .contains("in one or two sentences."));
+}
+
+#[test]
+fn critic_renders_the_rubric() {
+    let critic = Critic::new()
+        .with_rubric(&["Originality", "Evidence"])
+        .with_criterion("Structure")
+        .with_max_score(5);
+
+    let message = critic.system_message();
+
+    assert!(message.contains("following rubric:\n- Originality\n- Evidence\n- Structure\n"));
+    assert!(message.contains("a score from 1 to 5"));
+    assert!(!message.contains("Accuracy"));
+}
+
+#[test]
+fn translator_languages() {
+    let message = Translator::new("German").system_message();
+
+    assert!(message.contains("translate the provided context into German."));
+
+    let message = Translator::new("German")
+        .with_source_language("Japanese")
+        .system_message();
+
+    assert!(message.contains("translate the provided context from Japanese into German."));
+    assert_eq!(
+        Translator::new("German").description(),
+        "Translates text into German."
+    );
+}
+
+#[test]
+fn classifier_lists_labels_and_restricts_the_output() {
+    let classifier = Classifier::new(&["bug", "feature"])
+        .with_label("question")
+        .with_instructions("Anything asking how to do something is a question.");
+
+    let message = classifier.system_message();
+
+    assert!(message.contains("exactly one of the following labels:\n- bug\n- feature\n- question"));
+    assert!(message.contains("Anything asking how to do something is a question."));
+
+    let schema = classifier.output_schema().unwrap();
+
+    assert_eq!(
+        schema.schema()["properties"]["label"]["enum"],
+        serde_json::json!(["bug", "feature", "question"])
+    );
+    assert!(schema.validate(r#"{"label": "bug"}"#).is_ok());
+    assert!(schema.validate(r#"{"category": "bug"}"#).is_err());
+}
+
+#[test]
+#[should_panic(expected = "Classifier needs at least one label")]
+fn classifier_rejects_empty_labels() {
+    Classifier::new(&[]);
+}
+
+#[tokio::test]
+async fn classifier_output_is_typed() {
+    let model = Arc::new(MockModel::new().with_default_response(r#"{"label": "feature"}"#));
+
+    let pipeline = Pipeline::new()
+        .with_model(model)
+        .add_agent(Arc::new(Classifier::new(&["bug", "feature"])));
+
+    let res: Classification = pipeline
+        .run_pipeline_structured("Please add dark mode".into())
+        .await
+        .unwrap();
+
+    assert_eq!(res.label, "feature");
+}
+
+#[test]
+fn extractor_fields() {
+    let extractor = Extractor::new()
+        .with_field("customer", "The name of the customer")
+        .with_field("total", "The amount due, including tax");
+
+    let message = extractor.system_message();
+
+    assert!(message.contains(
+        "Extract the following fields:\n- customer: The name of the customer\n- total: The amount due, including tax"
+    ));
+    assert!(message.contains("use null for anything that can't be found"));
+
+    let schema = extractor.output_schema().unwrap();
+
+    assert_eq!(
+        schema.schema()["required"],
+        serde_json::json!(["customer", "total"])
+    );
+    assert!(schema
+        .validate(r#"{"customer": "Acme", "total": null}"#)
+        .is_ok());
+
+    let custom = OutputSchema::new("Invoice", serde_json::json!({ "type": "object" }));
+    let extractor = Extractor::new().with_output_schema(custom.clone());
+
+    assert_eq!(extractor.output_schema(), Some(custom));
+    assert!(!extractor.system_message().contains("following fields"));
+}
+
+#[test]
+fn code_reviewer_language_and_focus() {
+    let default = CodeReviewer::new().system_message();
+
+    assert!(default.contains("review the code in the provided context"));
+    assert!(default.contains("- security\n"));
+
+    let message = CodeReviewer::new()
+        .with_language("Rust")
+        .with_focus(&["unsafe code", "error handling"])
+        .system_message();
+
+    assert!(message.contains("review the Rust code"));
+    assert!(message.contains("concentrating on:\n- unsafe code\n- error handling\n"));
+    assert!(!message.contains("- security"));
+}
+
+#[test]
+fn planner_limits_the_steps() {
+    assert!(Planner::new()
+        .with_max_steps(3)
+        .system_message()
+        .contains("a numbered list of at most 3 steps"));
+
+    assert!(Planner::new()
+        .with_max_steps(0)
+        .system_message()
+        .contains("a numbered list of at most 1 step,"));
+
+    let planner = Planner::new()
+        .with_max_steps(3)
+        .with_output_schema(OutputSchema::of::<Plan>());
+
+    assert!(planner.system_message().contains("Plan at most 3 steps."));
+    assert!(!planner.system_message().contains("numbered list"));
+    assert_eq!(planner.output_schema().unwrap().name(), "Plan");
+}
+
+#[test]
+fn editor_modes_and_style_guide() {
+    let message = Editor::new().system_message();
+
+    assert!(message.contains("edit the provided context"));
+    assert!(!message.contains("style guide"));
+
+    let editor = Editor::new()
+        .with_proofread_only(true)
+        .with_style_guide("Use British spelling.");
+
+    let message = editor.system_message();
+
+    assert!(message.contains("Only fix spelling, grammar and punctuation"));
+    assert!(message.contains("Follow this style guide:\nUse British spelling."));
+    assert!(editor.description().starts_with("Proofreads"));
+}
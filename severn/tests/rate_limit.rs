@@ -0,0 +1,319 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde_json::json;
+use severn::errors::Error;
+use severn::models::{
+    BatchLimits, ChatRequest, Completion, EmbedModel, Message, MockModel, ModelSettings, OpenAI,
+    PromptModel, Usage,
+};
+use severn::rate_limit::{RateLimited, RateLimiter};
+use tokio::time::Instant;
+
+mod common;
+
+use common::openai_reply;
+use common::server::{MockResponse, MockServer};
+
+// A model that's rate limited a number of times before it answers
+struct Flaky {
+    failures: usize,
+    retry_after: Option<Duration>,
+    calls: AtomicUsize,
+}
+
+impl Flaky {
+    fn new(failures: usize, retry_after: Option<Duration>) -> Self {
+        Self {
+            failures,
+            retry_after,
+            calls: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl PromptModel for Flaky {
+    async fn chat(&self, _request: &ChatRequest) -> Result<Completion, Error> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+
+        match call < self.failures {
+            true => Err(Error::RateLimited {
+                retry_after: self.retry_after,
+            }),
+            false => Ok(Completion::new("done".into(), "flaky")),
+        }
+    }
+}
+
+fn request() -> ChatRequest {
+    ChatRequest::new()
+        .with_agent("Writer")
+        .with_message(Message::user("Write about rivers"))
+}
+
+#[tokio::test(start_paused = true)]
+async fn requests_wait_for_room_in_the_window() {
+    let limiter = RateLimiter::new().with_requests_per_minute(2);
+    let start = Instant::now();
+
+    limiter.acquire(1).await;
+    limiter.acquire(1).await;
+
+    assert_eq!(start.elapsed(), Duration::ZERO);
+
+    limiter.acquire(1).await;
+
+    assert!(start.elapsed() >= Duration::from_secs(60));
+}
+
+#[tokio::test(start_paused = true)]
+async fn tokens_wait_for_room_in_the_window() {
+    let limiter = RateLimiter::new().with_tokens_per_minute(100);
+    let start = Instant::now();
+
+    limiter.acquire(60).await;
+    limiter.acquire(40).await;
+
+    assert_eq!(start.elapsed(), Duration::ZERO);
+
+    limiter.acquire(1).await;
+
+    assert!(start.elapsed() >= Duration::from_secs(60));
+
+    //A request bigger than the whole budget still goes through once the window is empty
+    let start = Instant::now();
+
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    limiter.acquire(500).await;
+
+    assert_eq!(start.elapsed(), Duration::from_secs(60));
+}
+
+#[tokio::test(start_paused = true)]
+async fn pauses_hold_back_every_clone() {
+    let limiter = RateLimiter::new();
+    let clone = limiter.clone();
+    let start = Instant::now();
+
+    limiter.pause_for(Duration::from_secs(5));
+    //A shorter pause doesn't cut the longer one short
+    clone.pause_for(Duration::from_secs(1));
+
+    clone.acquire(1).await;
+
+    assert!(start.elapsed() >= Duration::from_secs(5));
+}
+
+#[tokio::test(start_paused = true)]
+async fn rate_limited_calls_are_retried_after_the_hint() {
+    let model = RateLimited::new(
+        Flaky::new(2, Some(Duration::from_secs(10))),
+        RateLimiter::new(),
+    );
+    let start = Instant::now();
+
+    let res = model.chat(&request()).await.unwrap();
+
+    assert_eq!(res.content, "done");
+    assert_eq!(model.model().calls.load(Ordering::SeqCst), 3);
+    assert!(start.elapsed() >= Duration::from_secs(20));
+}
+
+#[tokio::test(start_paused = true)]
+async fn retries_back_off_exponentially_without_a_hint() {
+    let model = RateLimited::new(Flaky::new(3, None), RateLimiter::new());
+    let start = Instant::now();
+
+    model.chat(&request()).await.unwrap();
+
+    //1s, then 2s, then 4s
+    assert!(start.elapsed() >= Duration::from_secs(7));
+    assert!(start.elapsed() < Duration::from_secs(8));
+}
+
+#[tokio::test(start_paused = true)]
+async fn retries_give_up_after_the_limit() {
+    let model = RateLimited::new(Flaky::new(5, None), RateLimiter::new().with_max_retries(1));
+
+    let res = model.chat(&request()).await;
+
+    assert!(matches!(res, Err(Error::RateLimited { .. })));
+    assert_eq!(model.model().calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn max_tokens_are_reserved_up_front() {
+    let model = RateLimited::new(
+        Flaky::new(0, None),
+        RateLimiter::new().with_tokens_per_minute(100),
+    );
+    //"Write about rivers" is estimated at 5 tokens, so this reserves 95
+    let request = request().with_settings(ModelSettings::new().with_max_tokens(90));
+    let start = Instant::now();
+
+    model.chat(&request).await.unwrap();
+    model.chat(&request).await.unwrap();
+
+    assert!(start.elapsed() >= Duration::from_secs(60));
+}
+
+#[tokio::test(start_paused = true)]
+async fn reservations_are_settled_with_the_reported_usage() {
+    let model = RateLimited::new(
+        MockModel::new()
+            .with_default_response("done")
+            .with_usage(Usage::new(5, 5)),
+        RateLimiter::new().with_tokens_per_minute(200),
+    );
+    //Each request reserves 155 tokens but only uses 10
+    let request = request().with_settings(ModelSettings::new().with_max_tokens(150));
+    let start = Instant::now();
+
+    model.chat(&request).await.unwrap();
+
+    let mut tokens = model.chat_stream(&request).await.unwrap();
+    while tokens.next().await.is_some() {}
+
+    model.chat(&request).await.unwrap();
+
+    assert_eq!(start.elapsed(), Duration::ZERO);
+}
+
+// An embedder that sends two chunks per request and is rate limited a number of times first
+struct Batched {
+    failures: usize,
+    calls: AtomicUsize,
+}
+
+#[async_trait]
+impl EmbedModel for Batched {
+    fn batch_limits(&self) -> BatchLimits {
+        BatchLimits::new().with_max_items(2)
+    }
+
+    async fn embed_file(&self, chunked_contents: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+
+        if call < self.failures {
+            return Err(Error::RateLimited { retry_after: None }.into());
+        }
+
+        Ok(chunked_contents.iter().map(|_| vec![1.0]).collect())
+    }
+
+    async fn embed_sentence(&self, _prompt: &str) -> anyhow::Result<Vec<f32>> {
+        Ok(vec![1.0])
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn embedding_batches_are_limited_one_by_one() {
+    let model = RateLimited::new(
+        Batched {
+            failures: 0,
+            calls: AtomicUsize::new(0),
+        },
+        RateLimiter::new().with_requests_per_minute(2),
+    );
+    let chunks = (0..5).map(|idx| idx.to_string()).collect::<Vec<String>>();
+    let start = Instant::now();
+
+    let embeddings = model.embed_file(chunks).await.unwrap();
+
+    assert_eq!(embeddings.len(), 5);
+    assert_eq!(model.model().calls.load(Ordering::SeqCst), 3);
+    assert!(start.elapsed() >= Duration::from_secs(60));
+}
+
+#[tokio::test(start_paused = true)]
+async fn embeddings_are_retried_too() {
+    let model = RateLimited::new(
+        Batched {
+            failures: 1,
+            calls: AtomicUsize::new(0),
+        },
+        RateLimiter::new(),
+    );
+
+    let embeddings = model.embed_file(vec!["rivers".into()]).await.unwrap();
+
+    assert_eq!(embeddings, vec![vec![1.0]]);
+    assert_eq!(model.model().calls.load(Ordering::SeqCst), 2);
+}
+
+fn rate_limit_error() -> MockResponse {
+    MockResponse::json(json!({
+        "error": {
+            "message": "Rate limit reached for gpt-4o. Please try again in 20ms.",
+            "type": "requests",
+            "param": null,
+            "code": "rate_limit_exceeded"
+        }
+    }))
+    .with_status(429)
+}
+
+#[tokio::test]
+async fn openai_returns_429s_without_retrying() {
+    let server = MockServer::start().await;
+    server.respond(rate_limit_error());
+
+    let res = OpenAI::compatible(server.url()).chat(&request()).await;
+
+    assert!(matches!(
+        res,
+        Err(Error::RateLimited { retry_after: Some(retry_after) })
+            if retry_after == Duration::from_millis(20)
+    ));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn openai_429s_are_retried_by_the_rate_limiter() {
+    let server = MockServer::start().await;
+    server.respond(rate_limit_error()).respond(openai_reply(
+        json!({"role": "assistant", "content": "It flows"}),
+    ));
+
+    let model = RateLimited::new(OpenAI::compatible(server.url()), RateLimiter::new());
+
+    let res = model.chat(&request()).await.unwrap();
+
+    assert_eq!(res.content, "It flows");
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn openai_quota_errors_arent_rate_limits() {
+    let server = MockServer::start().await;
+    server.respond(
+        MockResponse::json(json!({
+            "error": {
+                "message": "You exceeded your current quota.",
+                "type": "insufficient_quota",
+                "param": null,
+                "code": "insufficient_quota"
+            }
+        }))
+        .with_status(429),
+    );
+
+    let res = OpenAI::compatible(server.url()).chat(&request()).await;
+
+    assert!(matches!(res, Err(Error::LLMError(_))));
+}
+
+#[tokio::test]
+async fn openai_streams_return_429s_as_errors() {
+    let server = MockServer::start().await;
+    server.respond(rate_limit_error());
+
+    let res = OpenAI::compatible(server.url())
+        .chat_stream(&request())
+        .await;
+
+    assert!(matches!(res, Err(Error::RateLimited { .. })));
+}
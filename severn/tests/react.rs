@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use serde_json::json;
+use severn::agents::react::{ReActAction, ReActAgent, ReActStep};
+use severn::agents::traits::Agent;
+use severn::models::{OpenAI, Usage};
+use severn::pipeline::Pipeline;
+use severn::tools::MAX_TOOL_ITERATIONS;
+
+mod common;
+
+use common::server::{MockResponse, MockServer};
+use common::{openai_reply, Weather};
+
+fn agent() -> ReActAgent {
+    ReActAgent::new()
+        .with_tool(Arc::new(Weather))
+        .with_instructions("Only answer questions about the weather.")
+}
+
+fn weather_call(id: &str, thought: &str) -> MockResponse {
+    let arguments = json!({ "thought": thought, "city": "Paris" }).to_string();
+
+    openai_reply(json!({
+        "role": "assistant",
+        "content": null,
+        "tool_calls": [{
+            "id": id,
+            "type": "function",
+            "function": {"name": "get_weather", "arguments": arguments}
+        }]
+    }))
+}
+
+fn answer(content: &str) -> MockResponse {
+    openai_reply(json!({ "role": "assistant", "content": content }))
+}
+
+#[test]
+fn every_tool_asks_for_a_thought() {
+    let agent = agent();
+    let tools = agent.tools();
+    let parameters = tools[0].parameters();
+
+    assert_eq!(tools[0].name(), "get_weather");
+    assert_eq!(parameters["properties"]["thought"]["type"], "string");
+    assert_eq!(parameters["properties"]["city"]["type"], "string");
+    assert_eq!(parameters["required"], json!(["city", "thought"]));
+    assert!(agent
+        .system_message()
+        .ends_with("Only answer questions about the weather."));
+}
+
+#[tokio::test]
+async fn tool_calls_are_recorded_as_steps() {
+    let server = MockServer::start().await;
+    server
+        .respond(weather_call("call_1", "I need the weather"))
+        .respond(answer("Sunny"));
+
+    let run = agent()
+        .run(
+            "What's the weather in Paris?".into(),
+            OpenAI::compatible(server.url()),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(run.answer, "Sunny");
+    assert!(run.completed);
+    assert_eq!(
+        run.steps,
+        vec![ReActStep {
+            thought: "I need the weather".into(),
+            action: ReActAction {
+                tool: "get_weather".into(),
+                input: json!({ "city": "Paris" }),
+            },
+            observation: "It's sunny in Paris".into(),
+        }]
+    );
+    assert_eq!(run.usage, Some(Usage::new(20, 10)));
+
+    let requests = server.requests();
+    let first = requests[0].json();
+    let second = requests[1].json();
+
+    assert_eq!(
+        first["tools"][0]["function"]["parameters"]["required"],
+        json!(["city", "thought"])
+    );
+    assert_eq!(second["messages"][3]["content"], "It's sunny in Paris");
+}
+
+#[tokio::test]
+async fn calls_past_the_step_limit_are_refused() {
+    let server = MockServer::start().await;
+    server
+        .respond(weather_call("call_1", "I need the weather"))
+        .respond(weather_call("call_2", "Let me check again"))
+        .respond(answer("Sunny"));
+
+    let run = agent()
+        .with_max_steps(1)
+        .run("Weather?".into(), OpenAI::compatible(server.url()))
+        .await
+        .unwrap();
+
+    assert!(!run.completed);
+    assert_eq!(run.answer, "Sunny");
+    assert_eq!(run.steps.len(), 1);
+
+    let last = server.requests()[2].json();
+
+    assert_eq!(
+        last["messages"][5]["content"],
+        "Error: the step limit has been reached, give your final answer now"
+    );
+}
+
+#[tokio::test]
+async fn the_steps_are_kept_when_the_tool_loop_gives_up() {
+    let server = MockServer::start().await;
+
+    for i in 0..=MAX_TOOL_ITERATIONS {
+        server.respond(weather_call(&format!("call_{i}"), "Again"));
+    }
+
+    let run = agent()
+        .with_max_steps(20)
+        .run("Weather?".into(), OpenAI::compatible(server.url()))
+        .await
+        .unwrap();
+
+    assert!(!run.completed);
+    assert_eq!(run.answer, "");
+    assert_eq!(run.steps.len(), MAX_TOOL_ITERATIONS + 1);
+    assert!(run
+        .steps
+        .iter()
+        .all(|step| step.observation == "It's sunny in Paris"));
+}
+
+#[tokio::test]
+async fn react_agents_run_in_pipelines() {
+    let server = MockServer::start().await;
+    server
+        .respond(weather_call("call_1", "I need the weather"))
+        .respond(answer("Sunny"));
+
+    let pipeline = Pipeline::new()
+        .with_model(Arc::new(OpenAI::compatible(server.url())))
+        .add_agent(Arc::new(agent()));
+
+    let res = pipeline
+        .run_pipeline("What's the weather in Paris?".into())
+        .await;
+
+    assert_eq!(res.unwrap(), "Sunny");
+    assert_eq!(
+        server.requests()[1].json()["messages"][3]["content"],
+        "It's sunny in Paris"
+    );
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use severn::agents::premade::Researcher;
+use severn::errors::Error;
+use severn::models::MockModel;
+use severn::registry::{AgentRegistry, ConfigFormat};
+use severn::tools::Tool;
+
+struct Search;
+
+#[async_trait]
+impl Tool for Search {
+    fn name(&self) -> String {
+        "search".into()
+    }
+
+    fn description(&self) -> String {
+        "Searches the web".into()
+    }
+
+    fn parameters(&self) -> Value {
+        json!({ "type": "object" })
+    }
+
+    async fn call(&self, _arguments: Value) -> Result<String, Error> {
+        Ok(String::from("results"))
+    }
+}
+
+const TOML: &str = r#"
+name = "Support"
+description = "Answers customer questions"
+system_message = "You support customers of {company}. Reply in {locale}."
+tools = ["search"]
+
+[variables]
+locale = "English"
+
+[settings]
+temperature = 0.2
+"#;
+
+const TOML_LIST: &str = r#"
+[[agents]]
+name = "Greeter"
+system_message = "Greet the user."
+
+[[agents]]
+name = "Closer"
+description = "Ends the conversation"
+system_message = "Say goodbye."
+settings = { max_tokens = 50 }
+"#;
+
+#[cfg(feature = "yaml")]
+const YAML: &str = r#"
+agents:
+  - name: Greeter
+    system_message: Greet the user.
+  - name: Closer
+    description: Ends the conversation
+    system_message: Say goodbye.
+    settings:
+      max_tokens: 50
+"#;
+
+#[test]
+fn definitions_load_from_toml_and_json() {
+    let registry = AgentRegistry::new()
+        .with_tool(Arc::new(Search))
+        .load_str(TOML, ConfigFormat::Toml)
+        .unwrap()
+        .load_str(TOML_LIST, ConfigFormat::Toml)
+        .unwrap()
+        .load_str(
+            r#"{"name": "Tagger", "system_message": "Tag the text."}"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+
+    assert_eq!(
+        registry.names(),
+        vec!["Closer", "Greeter", "Support", "Tagger"]
+    );
+
+    let support = registry.get("Support").unwrap();
+
+    assert_eq!(support.description(), "Answers customer questions");
+    assert_eq!(
+        support.system_message(),
+        "You support customers of {company}. Reply in English."
+    );
+    assert_eq!(support.settings().temperature, Some(0.2));
+    assert_eq!(support.tools()[0].name(), "search");
+    assert_eq!(
+        support.variables(),
+        HashMap::from([("locale".to_string(), "English".to_string())])
+    );
+
+    assert_eq!(
+        registry.get("Closer").unwrap().settings().max_tokens,
+        Some(50)
+    );
+    assert!(matches!(registry.get("Writer"), Err(Error::NoAgentNamed(name)) if name == "Writer"));
+}
+
+#[test]
+fn bad_definitions_are_rejected() {
+    let res = AgentRegistry::new().load_str(TOML, ConfigFormat::Toml);
+
+    assert!(
+        matches!(res, Err(Error::InvalidAgentDefinition(message)) if message.contains("tool search"))
+    );
+
+    let res = AgentRegistry::new().load_str(r#"{"name": "NoMessage"}"#, ConfigFormat::Json);
+
+    assert!(
+        matches!(res, Err(Error::InvalidAgentDefinition(message)) if message.contains("system_message"))
+    );
+}
+
+#[test]
+fn duplicate_names_point_at_both_definitions() {
+    let res = AgentRegistry::new()
+        .register(Arc::new(Researcher::new()))
+        .unwrap()
+        .load_str(
+            r#"{"name": "Researcher", "system_message": "Research."}"#,
+            ConfigFormat::Json,
+        );
+
+    assert!(matches!(
+        res,
+        Err(Error::DuplicateAgent { name, first, second })
+            if name == "Researcher" && first == "Rust" && second == "a string"
+    ));
+
+    let dir = std::env::temp_dir().join(format!("severn-duplicates-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    std::fs::write(dir.join("a.toml"), TOML_LIST).unwrap();
+    std::fs::write(
+        dir.join("b.json"),
+        r#"{"name": "Closer", "system_message": "Bye."}"#,
+    )
+    .unwrap();
+
+    let res = AgentRegistry::new().load_dir(&dir);
+
+    let Err(Error::DuplicateAgent {
+        name,
+        first,
+        second,
+    }) = res
+    else {
+        panic!("expected a duplicate agent error");
+    };
+
+    assert_eq!(name, "Closer");
+    assert_eq!(first, dir.join("a.toml").display().to_string());
+    assert_eq!(second, dir.join("b.json").display().to_string());
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn definitions_load_from_yaml() {
+    let registry = AgentRegistry::new()
+        .load_str(YAML, ConfigFormat::Yaml)
+        .unwrap();
+
+    assert_eq!(registry.names(), vec!["Closer", "Greeter"]);
+    assert_eq!(
+        registry.get("Closer").unwrap().description(),
+        "Ends the conversation"
+    );
+    assert_eq!(
+        registry.get("Closer").unwrap().settings().max_tokens,
+        Some(50)
+    );
+
+    let res = registry.load_str("name: Greeter\nsystem_message: Hi", ConfigFormat::Yaml);
+
+    assert!(matches!(res, Err(Error::DuplicateAgent { .. })));
+}
+
+#[cfg(not(feature = "yaml"))]
+#[test]
+fn yaml_needs_the_feature() {
+    let res =
+        AgentRegistry::new().load_str("name: Greeter\nsystem_message: Hi", ConfigFormat::Yaml);
+
+    assert!(
+        matches!(res, Err(Error::InvalidAgentDefinition(message)) if message.contains("`yaml` feature"))
+    );
+}
+
+#[test]
+fn directories_are_loaded_in_order() {
+    let dir = std::env::temp_dir().join(format!("severn-registry-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    std::fs::write(dir.join("greeters.toml"), TOML_LIST).unwrap();
+    std::fs::write(
+        dir.join("tagger.json"),
+        r#"{"name": "Tagger", "system_message": "Tag the text."}"#,
+    )
+    .unwrap();
+    std::fs::write(dir.join("README.md"), "Not an agent").unwrap();
+
+    let registry = AgentRegistry::new().load_dir(&dir).unwrap();
+
+    assert_eq!(registry.len(), 3);
+    assert!(registry.contains("Tagger"));
+
+    let res = AgentRegistry::new().load_file(dir.join("README.md"));
+
+    assert!(matches!(res, Err(Error::InvalidAgentDefinition(_))));
+}
+
+#[tokio::test]
+async fn pipelines_are_built_by_name() {
+    let registry = AgentRegistry::new()
+        .register(Arc::new(Researcher::new()))
+        .unwrap()
+        .load_str(TOML_LIST, ConfigFormat::Toml)
+        .unwrap();
+
+    let model = Arc::new(MockModel::new().with_default_response("done"));
+
+    let pipeline = registry
+        .pipeline(&["Researcher", "Closer"])
+        .unwrap()
+        .with_model(model.clone());
+
+    pipeline.run_pipeline("Hello".into()).await.unwrap();
+
+    let calls = model.calls();
+
+    assert_eq!(calls[0].agent, "Researcher");
+    assert_eq!(calls[1].agent, "Closer");
+    assert_eq!(calls[1].settings.max_tokens, Some(50));
+
+    assert!(matches!(
+        registry.pipeline(&["Researcher", "Writer"]),
+        Err(Error::NoAgentNamed(_))
+    ));
+}
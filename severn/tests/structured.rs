@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+use severn::errors::Error;
+use severn::models::{Anthropic, ChatRequest, Message, MockModel, OpenAI, PromptModel};
+use severn::pipeline::Pipeline;
+use severn::structured::{parse_output, OutputSchema};
+
+mod common;
+
+use common::agent;
+use common::openai_reply;
+use common::server::{MockResponse, MockServer};
+
+#[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+struct Forecast {
+    city: String,
+    temperature: i64,
+}
+
+fn request() -> ChatRequest {
+    ChatRequest::new()
+        .with_agent("Forecaster")
+        .with_system_message("You are Forecaster")
+        .with_message(Message::user("What's the weather in Bristol?"))
+        .with_output_schema(Some(OutputSchema::of::<Forecast>()))
+}
+
+#[test]
+fn names_are_sanitised() {
+    let schema = OutputSchema::new("weather report (daily)", json!({"type": "object"}));
+
+    assert_eq!(schema.name(), "weather_report__daily_");
+}
+
+#[test]
+fn instructions_include_the_schema() {
+    let schema = OutputSchema::of::<Forecast>();
+    let instructions = schema.instructions();
+
+    assert!(instructions.starts_with("Respond only with a JSON value"));
+    assert!(instructions.contains("\n\n{\n"));
+    assert!(instructions.contains("\"temperature\""));
+}
+
+#[test]
+fn validate_checks_the_type_and_required_properties() {
+    let schema = OutputSchema::of::<Forecast>();
+
+    let valid = schema.validate("{\"city\": \"Bristol\", \"temperature\": 18}");
+    let missing = schema.validate("{\"city\": \"Bristol\"}");
+    let wrong_type = schema.validate("[\"Bristol\", 18]");
+    let not_json = schema.validate("It's 18 degrees in Bristol");
+
+    assert_eq!(valid.unwrap()["temperature"], 18);
+    assert!(matches!(
+        missing,
+        Err(Error::InvalidStructuredOutput(message)) if message == "missing required property `temperature`"
+    ));
+    assert!(matches!(
+        wrong_type,
+        Err(Error::InvalidStructuredOutput(message)) if message == "expected a JSON object"
+    ));
+    assert!(matches!(not_json, Err(Error::InvalidStructuredOutput(_))));
+}
+
+#[test]
+fn code_fences_are_ignored() {
+    let schema = OutputSchema::of::<Forecast>();
+    let output = "```json\n{\"city\": \"Bristol\", \"temperature\": 18}\n```";
+
+    assert!(schema.validate(output).is_ok());
+    assert_eq!(
+        parse_output::<Forecast>(output).unwrap(),
+        Forecast {
+            city: "Bristol".into(),
+            temperature: 18
+        }
+    );
+}
+
+#[tokio::test]
+async fn openai_sends_the_schema_as_the_response_format() {
+    let server = MockServer::start().await;
+    server.respond(openai_reply(json!({
+        "role": "assistant",
+        "content": "{\"city\": \"Bristol\", \"temperature\": 18}"
+    })));
+
+    OpenAI::compatible(server.url())
+        .chat(&request())
+        .await
+        .unwrap();
+
+    let body = server.requests()[0].json();
+    let format = &body["response_format"];
+
+    assert_eq!(format["type"], "json_schema");
+    assert_eq!(format["json_schema"]["name"], "Forecast");
+    assert_eq!(
+        format["json_schema"]["schema"]["required"],
+        json!(["city", "temperature"])
+    );
+    assert_eq!(body["messages"][0]["content"], "You are Forecaster");
+}
+
+#[tokio::test]
+async fn anthropic_asks_for_the_schema_in_the_system_message() {
+    let server = MockServer::start().await;
+    server.respond(MockResponse::json(json!({
+        "content": [{"type": "text", "text": "{\"city\": \"Bristol\", \"temperature\": 18}"}]
+    })));
+
+    Anthropic::from_api_key("test-key")
+        .unwrap()
+        .with_base_url(server.url())
+        .chat(&request())
+        .await
+        .unwrap();
+
+    let body = server.requests()[0].json();
+    let system = body["system"].as_str().unwrap();
+
+    assert!(system.starts_with("You are Forecaster\n\nRespond only with a JSON value"));
+    assert!(system.contains("\"temperature\""));
+}
+
+#[tokio::test]
+async fn output_that_does_not_match_is_an_error() {
+    let server = MockServer::start().await;
+    server.respond(MockResponse::json(json!({
+        "content": [{"type": "text", "text": "It's 18 degrees in Bristol"}]
+    })));
+
+    let res = Anthropic::from_api_key("test-key")
+        .unwrap()
+        .with_base_url(server.url())
+        .chat(&request())
+        .await;
+
+    assert!(matches!(res, Err(Error::InvalidStructuredOutput(_))));
+}
+
+#[tokio::test]
+async fn pipelines_deserialize_the_last_output() {
+    let model = MockModel::new().with_agent_response(
+        "Forecaster",
+        "```json\n{\"city\": \"Bristol\", \"temperature\": 18}\n```",
+    );
+
+    let pipeline = Pipeline::new()
+        .with_model(Arc::new(model))
+        .add_agent(agent("Forecaster"));
+
+    let forecast: Forecast = pipeline
+        .run_pipeline_structured("What's the weather in Bristol?".into())
+        .await
+        .unwrap();
+
+    assert_eq!(forecast.temperature, 18);
+
+    let res = pipeline
+        .run_pipeline_structured::<Vec<Forecast>>("What's the weather?".into())
+        .await;
+
+    assert!(matches!(res, Err(Error::InvalidStructuredOutput(_))));
+}
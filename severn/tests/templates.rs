@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use severn::agents::premade::ArticleWriter;
+use severn::agents::traits::Agent;
+use severn::blackboard::Blackboard;
+use severn::errors::Error;
+use severn::models::{Message, MockModel};
+use severn::pipeline::Pipeline;
+use severn::templates::Template;
+
+struct Greeter;
+
+impl Agent for Greeter {
+    fn name(&self) -> String {
+        "Greeter".into()
+    }
+
+    fn system_message(&self) -> String {
+        "Greet the customer".into()
+    }
+
+    fn system_template(&self) -> Option<Template> {
+        Some(Template::new(
+            "Greet {customer} in {locale}. Reply as {{\"greeting\": \"...\"}}.",
+        ))
+    }
+
+    fn variables(&self) -> HashMap<String, String> {
+        HashMap::from([("locale".to_string(), "English".to_string())])
+    }
+}
+
+fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn templates_fill_placeholders_and_keep_escapes() {
+    let template =
+        Template::new("Hi {name}, your {item.kind} is {{ready}}. {name}! { not a placeholder }");
+
+    assert_eq!(template.variables(), vec!["name", "item.kind"]);
+    assert_eq!(
+        template.missing_variables(&values(&[("name", "Jo")])),
+        vec!["item.kind"]
+    );
+    assert_eq!(
+        template.fill(&values(&[("name", "Jo"), ("item.kind", "order")])),
+        "Hi Jo, your order is {ready}. Jo! { not a placeholder }"
+    );
+    assert_eq!(
+        template.fill(&values(&[("name", "Jo")])),
+        "Hi Jo, your {item.kind} is {ready}. Jo! { not a placeholder }"
+    );
+}
+
+#[tokio::test]
+async fn run_variables_and_blackboard_fill_the_template() {
+    let model = Arc::new(MockModel::new().with_default_response("Bonjour"));
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .add_agent(Arc::new(Greeter));
+
+    pipeline
+        .run_pipeline_with_variables(
+            "Say hello".into(),
+            values(&[("customer", "Acme"), ("locale", "French")]),
+        )
+        .await
+        .unwrap();
+
+    pipeline
+        .run_pipeline_with_blackboard(
+            "Say hello".into(),
+            Blackboard::new().with_value("customer", "Globex").unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let calls = model.calls();
+
+    assert_eq!(
+        calls[0].messages[0],
+        Message::system("Greet Acme in French. Reply as {\"greeting\": \"...\"}.")
+    );
+    assert_eq!(
+        calls[1].messages[0],
+        Message::system("Greet Globex in English. Reply as {\"greeting\": \"...\"}.")
+    );
+}
+
+#[tokio::test]
+async fn unresolved_placeholders_are_reported_before_sending() {
+    let model = Arc::new(MockModel::new().with_default_response("Hello"));
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .add_agent(Arc::new(Greeter));
+
+    let res = pipeline.run_pipeline("Say hello".into()).await;
+
+    assert!(matches!(
+        res,
+        Err(Error::UnresolvedPlaceholders { agent, placeholders })
+            if agent == "Greeter" && placeholders == vec!["customer"]
+    ));
+    assert!(model.calls().is_empty());
+}
+
+#[tokio::test]
+async fn article_writers_can_be_retargeted_per_run() {
+    let model = Arc::new(MockModel::new().with_default_response("An article"));
+
+    let pipeline = Pipeline::new()
+        .with_model(model.clone())
+        .add_agent(Arc::new(ArticleWriter::new()));
+
+    pipeline
+        .run_pipeline_with_variables(
+            "Write about Rust".into(),
+            values(&[("target_audience", "students")]),
+        )
+        .await
+        .unwrap();
+
+    let system = model.calls()[0].messages[0].text();
+
+    assert!(system.contains("Your target audience is students."));
+    assert!(system.contains("your tone should be: concise."));
+}
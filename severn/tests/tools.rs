@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use severn::agents::traits::Agent;
+use severn::errors::Error;
+use severn::models::{Anthropic, Ollama, OpenAI, PromptModel};
+use severn::tools::{Tool, MAX_TOOL_ITERATIONS};
+
+mod common;
+
+use common::server::{MockResponse, MockServer};
+use common::{openai_reply, Weather};
+
+// A tool that always fails
+struct Broken;
+
+#[async_trait]
+impl Tool for Broken {
+    fn name(&self) -> String {
+        "broken".into()
+    }
+
+    fn description(&self) -> String {
+        "Never works.".into()
+    }
+
+    fn parameters(&self) -> Value {
+        json!({"type": "object", "properties": {}})
+    }
+
+    async fn call(&self, _arguments: Value) -> Result<String, Error> {
+        Err(Error::OptionIsNone)
+    }
+}
+
+struct Forecaster;
+
+impl Agent for Forecaster {
+    fn name(&self) -> String {
+        "Forecaster".into()
+    }
+
+    fn system_message(&self) -> String {
+        "You are Forecaster".into()
+    }
+
+    fn tools(&self) -> Vec<Arc<dyn Tool>> {
+        vec![Arc::new(Weather), Arc::new(Broken)]
+    }
+}
+
+async fn prompt<P: PromptModel>(model: &P) -> Result<String, Error> {
+    let agent: Arc<dyn Agent> = Arc::new(Forecaster);
+
+    model
+        .prompt("What's the weather in Bristol?", "None".into(), &agent)
+        .await
+}
+
+fn openai_tool_call(id: &str, name: &str, arguments: &str) -> MockResponse {
+    openai_reply(json!({
+        "role": "assistant",
+        "content": null,
+        "tool_calls": [{
+            "id": id,
+            "type": "function",
+            "function": {"name": name, "arguments": arguments}
+        }]
+    }))
+}
+
+#[tokio::test]
+async fn openai_runs_tools_until_there_is_an_answer() {
+    let server = MockServer::start().await;
+    server
+        .respond(openai_tool_call(
+            "call_1",
+            "get_weather",
+            "{\"city\": \"Bristol\"}",
+        ))
+        .respond(openai_reply(
+            json!({"role": "assistant", "content": "It's sunny."}),
+        ));
+
+    let res = prompt(&OpenAI::compatible(server.url())).await.unwrap();
+
+    assert_eq!(res, "It's sunny.");
+
+    let requests = server.requests();
+    let first = requests[0].json();
+    let second = requests[1].json();
+
+    assert_eq!(first["tools"][0]["type"], "function");
+    assert_eq!(first["tools"][0]["function"]["name"], "get_weather");
+    assert_eq!(
+        first["tools"][0]["function"]["parameters"]["required"],
+        json!(["city"])
+    );
+
+    assert_eq!(second["messages"][2]["role"], "assistant");
+    assert_eq!(second["messages"][2]["tool_calls"][0]["id"], "call_1");
+    assert_eq!(
+        second["messages"][3],
+        json!({"role": "tool", "content": "It's sunny in Bristol", "tool_call_id": "call_1"})
+    );
+}
+
+#[tokio::test]
+async fn mistakes_are_reported_back_to_the_model() {
+    let server = MockServer::start().await;
+    server
+        .respond(openai_tool_call("call_1", "get_tides", "{}"))
+        .respond(openai_tool_call("call_2", "get_weather", "{\"city\": "))
+        .respond(openai_tool_call("call_3", "broken", ""))
+        .respond(openai_reply(
+            json!({"role": "assistant", "content": "I can't tell."}),
+        ));
+
+    let res = prompt(&OpenAI::compatible(server.url())).await.unwrap();
+
+    assert_eq!(res, "I can't tell.");
+
+    let last = server.requests()[3].json();
+    let results = last["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|message| message["role"] == "tool")
+        .map(|message| message["content"].as_str().unwrap().to_owned())
+        .collect::<Vec<String>>();
+
+    assert_eq!(results[0], "Error: there is no tool called get_tides");
+    assert!(results[1].starts_with("Error: the arguments were not valid JSON"));
+    assert_eq!(results[2], "Error: Option expected to be Some but is None");
+}
+
+#[tokio::test]
+async fn anthropic_sends_tool_results_back() {
+    let server = MockServer::start().await;
+    server
+        .respond(MockResponse::json(json!({
+            "content": [{
+                "type": "tool_use",
+                "id": "toolu_1",
+                "name": "get_weather",
+                "input": {"city": "Bristol"}
+            }]
+        })))
+        .respond(MockResponse::json(json!({
+            "content": [{"type": "text", "text": "It's sunny."}]
+        })));
+
+    let model = Anthropic::from_api_key("test-key")
+        .unwrap()
+        .with_base_url(server.url());
+
+    let res = prompt(&model).await.unwrap();
+
+    assert_eq!(res, "It's sunny.");
+
+    let requests = server.requests();
+    let first = requests[0].json();
+    let second = requests[1].json();
+
+    assert_eq!(first["tools"][0]["name"], "get_weather");
+    assert_eq!(
+        first["tools"][0]["input_schema"]["required"],
+        json!(["city"])
+    );
+    assert_eq!(second["messages"][1]["role"], "assistant");
+    assert_eq!(
+        second["messages"][2]["content"][0],
+        json!({"type": "tool_result", "tool_use_id": "toolu_1", "content": "It's sunny in Bristol"})
+    );
+}
+
+#[tokio::test]
+async fn openai_stops_at_the_tool_loop_limit() {
+    let server = MockServer::start().await;
+
+    for idx in 0..=MAX_TOOL_ITERATIONS {
+        server.respond(openai_tool_call(
+            &format!("call_{idx}"),
+            "get_weather",
+            "{\"city\": \"Bristol\"}",
+        ));
+    }
+
+    let res = prompt(&OpenAI::compatible(server.url())).await;
+
+    assert!(matches!(
+        res,
+        Err(Error::ToolLoopLimit(agent, limit)) if agent == "Forecaster" && limit == MAX_TOOL_ITERATIONS
+    ));
+    assert_eq!(server.requests().len(), MAX_TOOL_ITERATIONS + 1);
+}
+
+#[tokio::test]
+async fn anthropic_stops_at_the_tool_loop_limit() {
+    let server = MockServer::start().await;
+
+    for idx in 0..=MAX_TOOL_ITERATIONS {
+        server.respond(MockResponse::json(json!({
+            "content": [{
+                "type": "tool_use",
+                "id": format!("toolu_{idx}"),
+                "name": "get_weather",
+                "input": {"city": "Bristol"}
+            }]
+        })));
+    }
+
+    let model = Anthropic::from_api_key("test-key")
+        .unwrap()
+        .with_base_url(server.url());
+
+    let res = prompt(&model).await;
+
+    assert!(matches!(
+        res,
+        Err(Error::ToolLoopLimit(_, MAX_TOOL_ITERATIONS))
+    ));
+    assert_eq!(server.requests().len(), MAX_TOOL_ITERATIONS + 1);
+}
+
+#[tokio::test]
+async fn ollama_stops_at_the_tool_loop_limit() {
+    let server = MockServer::start().await;
+
+    for _ in 0..=MAX_TOOL_ITERATIONS {
+        server.respond(MockResponse::json(json!({
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{"function": {"name": "get_weather", "arguments": {"city": "Bristol"}}}]
+            },
+            "done": true
+        })));
+    }
+
+    let res = prompt(&Ollama::new("llama3").with_base_url(server.url())).await;
+
+    assert!(matches!(
+        res,
+        Err(Error::ToolLoopLimit(_, MAX_TOOL_ITERATIONS))
+    ));
+    assert_eq!(server.requests().len(), MAX_TOOL_ITERATIONS + 1);
+}
@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use severn::models::{MockModel, ModelPrice, ModelSettings, PriceTable, Usage, UsageReport};
+use severn::pipeline::{Pipeline, PipelineEvent};
+
+mod common;
+
+use common::{agent, TestAgent};
+
+fn prices() -> PriceTable {
+    PriceTable::new()
+        .with_price("gpt-4o", ModelPrice::per_million_tokens(2.5, 10.0))
+        .with_price("gpt-4o-mini", ModelPrice::per_million_tokens(0.15, 0.6))
+}
+
+fn assert_close(left: f64, right: f64) {
+    assert!((left - right).abs() < 1e-9, "{left} != {right}");
+}
+
+#[test]
+fn prices_match_the_longest_prefix() {
+    let prices = prices();
+
+    assert_eq!(
+        prices.price("gpt-4o-mini-2024-07-18"),
+        Some(ModelPrice::per_million_tokens(0.15, 0.6))
+    );
+    assert_eq!(
+        prices.price("gpt-4o-2024-08-06"),
+        Some(ModelPrice::per_million_tokens(2.5, 10.0))
+    );
+    assert_eq!(prices.price("claude-3-5-sonnet-latest"), None);
+
+    assert_close(
+        prices
+            .cost("gpt-4o", &Usage::new(1_000_000, 100_000))
+            .unwrap(),
+        3.5,
+    );
+}
+
+#[test]
+fn reports_merge_per_agent() {
+    let prices = prices();
+
+    let mut first = UsageReport::new();
+    first.record("Writer", "gpt-4o", Some(Usage::new(1000, 500)), &prices);
+
+    let mut second = UsageReport::new();
+    second.record("Writer", "gpt-4o", Some(Usage::new(1000, 500)), &prices);
+    second.record("Checker", "unknown", Some(Usage::new(10, 10)), &prices);
+
+    first.merge(&second);
+
+    let writer = first.agent("Writer").unwrap();
+
+    assert_eq!(writer.calls, 2);
+    assert_eq!(writer.usage, Usage::new(2000, 1000));
+    assert_close(writer.cost, 0.015);
+
+    assert_eq!(first.total().calls, 3);
+    assert_eq!(first.total().unpriced_calls, 1);
+    assert_eq!(first.total().usage, Usage::new(2010, 1010));
+}
+
+#[tokio::test]
+async fn pipeline_reports_usage_per_agent() {
+    let model = Arc::new(
+        MockModel::new()
+            .with_default_response("ok")
+            .with_usage(Usage::new(1000, 200)),
+    );
+
+    let pipeline = Pipeline::new()
+        .with_model(model)
+        .with_prices(prices())
+        .add_agent(
+            TestAgent::new("Researcher")
+                .with_settings(ModelSettings::new().with_model("gpt-4o-mini"))
+                .build(),
+        )
+        .add_agent(
+            TestAgent::new("Writer")
+                .with_settings(ModelSettings::new().with_model("gpt-4o"))
+                .build(),
+        );
+
+    let (res, report) = pipeline
+        .run_pipeline_with_usage("Hello".into())
+        .await
+        .unwrap();
+
+    assert_eq!(res, "ok");
+
+    let researcher = report.agent("Researcher").unwrap();
+    let writer = report.agent("Writer").unwrap();
+
+    assert_close(researcher.cost, 0.00027);
+    assert_close(writer.cost, 0.0045);
+    assert_eq!(report.total().usage, Usage::new(2000, 400));
+    assert_close(report.total().cost, 0.00477);
+}
+
+#[tokio::test]
+async fn unpriced_models_are_counted() {
+    let model = Arc::new(MockModel::new().with_default_response("ok"));
+
+    let pipeline = Pipeline::new()
+        .with_model(model)
+        .with_prices(prices())
+        .add_agent(agent("Writer"));
+
+    let (_, report) = pipeline
+        .run_pipeline_with_usage("Hello".into())
+        .await
+        .unwrap();
+
+    assert_eq!(report.total().calls, 1);
+    assert_eq!(report.total().unpriced_calls, 1);
+    assert_close(report.total().cost, 0.0);
+}
+
+#[tokio::test]
+async fn streaming_reports_usage() {
+    let model = Arc::new(
+        MockModel::new()
+            .with_default_response("ok")
+            .with_usage(Usage::new(1000, 200)),
+    );
+
+    let pipeline = Pipeline::new()
+        .with_model(model)
+        .with_prices(prices())
+        .add_agent(
+            TestAgent::new("Researcher")
+                .with_settings(ModelSettings::new().with_model("gpt-4o-mini"))
+                .build(),
+        )
+        .add_agent(
+            TestAgent::new("Writer")
+                .with_settings(ModelSettings::new().with_model("gpt-4o"))
+                .build(),
+        );
+
+    let events = pipeline
+        .run_pipeline_stream("Hello".into(), false)
+        .map(Result::unwrap)
+        .collect::<Vec<PipelineEvent>>()
+        .await;
+
+    let Some(PipelineEvent::Done { usage, report, .. }) = events.last() else {
+        panic!("the last event should be Done");
+    };
+
+    assert_eq!(*usage, Some(Usage::new(1000, 200)));
+    assert_eq!(report.total().calls, 2);
+    assert_close(report.total().cost, 0.00477);
+}